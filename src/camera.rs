@@ -0,0 +1,267 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_inspector_egui::{inspector_options::ReflectInspectorOptions, InspectorOptions};
+
+use crate::{distortion::LensDistortion, MainPointsParent};
+
+/// The camera model: intrinsics (and friends) the image planes and points are derived from
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraIntrinsics>()
+            .register_type::<CameraIntrinsics>()
+            .init_resource::<CameraExtrinsics>()
+            .register_type::<CameraExtrinsics>()
+            .init_resource::<CameraProjection>()
+            .register_type::<CameraProjection>()
+            .register_type::<ProjectionModel>()
+            .add_systems(
+                Update,
+                apply_extrinsics.run_if(resource_changed::<CameraExtrinsics>),
+            );
+    }
+}
+
+/// Pinhole intrinsics, i.e. the entries of the K matrix:
+///
+/// ```text
+///     | fx  skew  cx |
+/// K = |  0   fy   cy |
+///     |  0    0    1 |
+/// ```
+///
+/// These are expressed in the same metric units as the sensor ([`crate::ImageSize`]),
+/// with the sensor origin in its centre and +Y up.
+/// The identity K maps the sensor 1:1 onto the image plane at Z=1.
+#[derive(Debug, Resource, Reflect, PartialEq, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct CameraIntrinsics {
+    #[inspector(min = 0.1, max = 5.0, speed = 0.01)]
+    pub fx: f32,
+    #[inspector(min = 0.1, max = 5.0, speed = 0.01)]
+    pub fy: f32,
+
+    #[inspector(min = -1.0, max = 1.0, speed = 0.01)]
+    pub cx: f32,
+    #[inspector(min = -1.0, max = 1.0, speed = 0.01)]
+    pub cy: f32,
+
+    #[inspector(min = -1.0, max = 1.0, speed = 0.01)]
+    pub skew: f32,
+}
+
+impl Default for CameraIntrinsics {
+    fn default() -> Self {
+        Self {
+            fx: 1.0,
+            fy: 1.0,
+            cx: 0.0,
+            cy: 0.0,
+            skew: 0.0,
+        }
+    }
+}
+
+impl CameraIntrinsics {
+    /// The K matrix
+    pub fn matrix(&self) -> Mat3 {
+        Mat3::from_cols(
+            Vec3::new(self.fx, 0.0, 0.0),
+            Vec3::new(self.skew, self.fy, 0.0),
+            Vec3::new(self.cx, self.cy, 1.0),
+        )
+    }
+
+    /// Sensor position to normalized image coordinates, i.e. K⁻¹ applied to (x, y, 1)
+    pub fn normalize(&self, sensor: Vec2) -> Vec2 {
+        let y = (sensor.y - self.cy) / self.fy;
+        let x = (sensor.x - self.cx - self.skew * y) / self.fx;
+
+        Vec2::new(x, y)
+    }
+
+    /// Normalized image coordinates to sensor position, i.e. K applied to (x, y, 1)
+    pub fn denormalize(&self, normalized: Vec2) -> Vec2 {
+        Vec2::new(
+            self.fx * normalized.x + self.skew * normalized.y + self.cx,
+            self.fy * normalized.y + self.cy,
+        )
+    }
+
+    /// Where the ray through the given sensor position crosses the plane at the given depth,
+    /// under perspective projection
+    pub fn back_project(&self, sensor: Vec2, depth: f32) -> Vec3 {
+        self.normalize(sensor).extend(1.0) * depth
+    }
+
+    /// Where a point in camera space lands on the sensor, if it is in front of the camera
+    pub fn project(&self, point: Vec3) -> Option<Vec2> {
+        (point.z > f32::EPSILON).then(|| self.denormalize(point.xy() / point.z))
+    }
+
+    /// Where a world space point lands on the sensor of a pinhole camera placed by
+    /// `camera_to_world`, if it is in front of it
+    pub fn project_world(&self, camera_to_world: &GlobalTransform, world: Vec3) -> Option<Vec2> {
+        self.project(camera_to_world.affine().inverse().transform_point3(world))
+    }
+}
+
+/// The camera pose as [R|t], mapping world space into camera space:
+///
+/// ```text
+/// x_cam = R * x_world + t
+/// ```
+///
+/// The camera rig ([`MainPointsParent`]) is placed accordingly, so changing this moves the
+/// image planes and their points while world geometry stays put.
+#[derive(Debug, Default, Resource, Reflect, PartialEq)]
+#[reflect(Resource)]
+pub struct CameraExtrinsics {
+    /// Euler angles (XYZ order, in degrees) of R
+    pub rotation: Vec3,
+    pub translation: Vec3,
+}
+
+impl CameraExtrinsics {
+    pub fn rotation_quat(&self) -> Quat {
+        let r = self.rotation;
+        Quat::from_euler(
+            EulerRot::XYZ,
+            r.x.to_radians(),
+            r.y.to_radians(),
+            r.z.to_radians(),
+        )
+    }
+
+    /// R
+    pub fn rotation_matrix(&self) -> Mat3 {
+        Mat3::from_quat(self.rotation_quat())
+    }
+
+    /// Where the camera is in world space, i.e. -Rᵀt
+    pub fn optical_centre(&self) -> Vec3 {
+        -(self.rotation_quat().inverse() * self.translation)
+    }
+
+    pub fn world_to_camera(&self, point: Vec3) -> Vec3 {
+        self.rotation_quat() * point + self.translation
+    }
+
+    pub fn camera_to_world(&self) -> Transform {
+        Transform::from_translation(self.optical_centre())
+            .with_rotation(self.rotation_quat().inverse())
+    }
+}
+
+fn apply_extrinsics(
+    extrinsics: Res<CameraExtrinsics>,
+    rig: Res<MainPointsParent>,
+    mut transforms: Query<&mut Transform>,
+) {
+    let Ok(mut transform) = transforms.get_mut(**rig) else {
+        warn!("unexpected camera rig missing");
+        return;
+    };
+
+    *transform = extrinsics.camera_to_world();
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+pub enum ProjectionModel {
+    /// Central projection through the optical centre
+    #[default]
+    Perspective,
+
+    /// Parallel projection along the optical axis, no scaling with depth
+    Orthographic,
+
+    /// Orthographic, then scaled as if every point was at the reference depth
+    WeakPerspective,
+
+    /// Parallel projection along the direction of the reference point, then scaled as if every
+    /// point was at the reference depth
+    Paraperspective,
+}
+
+/// Which camera model maps camera space onto the image planes.
+/// The affine models approximate perspective around a reference point.
+#[derive(Debug, Resource, Reflect, PartialEq)]
+#[reflect(Resource)]
+pub struct CameraProjection {
+    pub model: ProjectionModel,
+
+    /// Camera space point which the affine models are built around, e.g. the centroid of the scene
+    pub reference: Vec3,
+}
+
+impl Default for CameraProjection {
+    fn default() -> Self {
+        Self {
+            model: ProjectionModel::default(),
+            reference: Vec3::new(0.0, 0.0, 4.0),
+        }
+    }
+}
+
+impl CameraProjection {
+    /// Maps normalized image coordinates (as XY, Z=0) onto the image plane at the given depth.
+    /// Under perspective this is just a scaling, under the affine models the planes do not
+    /// grow with depth and the rays are parallel.
+    pub fn plane_transform(&self, depth: f32) -> Transform {
+        let reference_depth = self.reference.z.max(f32::EPSILON);
+
+        let (offset, scale) = match self.model {
+            ProjectionModel::Perspective => (Vec2::ZERO, depth),
+            ProjectionModel::Orthographic => (Vec2::ZERO, 1.0),
+            ProjectionModel::WeakPerspective => (Vec2::ZERO, reference_depth),
+            ProjectionModel::Paraperspective => (
+                self.reference.xy() / reference_depth * (depth - reference_depth),
+                reference_depth,
+            ),
+        };
+
+        Transform::from_translation(offset.extend(depth)).with_scale(Vec3::new(scale, scale, 1.0))
+    }
+
+    /// Where the ray of the given normalized image coordinates crosses the plane at the given depth
+    pub fn point_on_plane(&self, normalized: Vec2, depth: f32) -> Vec3 {
+        self.plane_transform(depth)
+            .transform_point(normalized.extend(0.0))
+    }
+
+    /// The normalized image coordinates of a point in camera space,
+    /// if it is in front of the camera (perspective only)
+    pub fn project(&self, point: Vec3) -> Option<Vec2> {
+        let reference_depth = self.reference.z.max(f32::EPSILON);
+
+        match self.model {
+            ProjectionModel::Perspective => (point.z > f32::EPSILON).then(|| point.xy() / point.z),
+            ProjectionModel::Orthographic => Some(point.xy()),
+            ProjectionModel::WeakPerspective => Some(point.xy() / reference_depth),
+            ProjectionModel::Paraperspective => {
+                let direction = self.reference.xy() / reference_depth;
+                Some((point.xy() - direction * (point.z - reference_depth)) / reference_depth)
+            }
+        }
+    }
+}
+
+/// Everything which maps camera space onto the image planes and the sensor
+#[derive(SystemParam)]
+pub struct CameraModel<'w> {
+    pub intrinsics: Res<'w, CameraIntrinsics>,
+    pub projection: Res<'w, CameraProjection>,
+    pub distortion: Res<'w, LensDistortion>,
+}
+
+impl CameraModel<'_> {
+    /// Where the ray through the given sensor position crosses the plane at the given depth
+    pub fn back_project(&self, sensor: Vec2, depth: f32) -> Vec3 {
+        self.projection
+            .point_on_plane(self.intrinsics.normalize(sensor), depth)
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.intrinsics.is_changed() || self.projection.is_changed()
+    }
+}
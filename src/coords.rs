@@ -0,0 +1,38 @@
+//! Conversions between the coordinate systems of the image.
+//!
+//! - Metric: Sensor units (see [`crate::ImageSize`]), origin in the centre, +Y up.
+//! - Pixel: OpenCV convention, origin in the top left, +Y down.
+//!   Integer coordinates are pixel centres, so the top left corner of the image is at (-0.5, -0.5).
+//! - NDC: Normalized device coordinates, [-1, 1] across the image, origin in the centre, +Y up.
+
+use bevy::prelude::*;
+
+pub fn metric_to_pixel(metric: Vec2, size: Vec2, resolution: UVec2) -> Vec2 {
+    ndc_to_pixel(metric_to_ndc(metric, size), resolution)
+}
+
+pub fn pixel_to_metric(pixel: Vec2, size: Vec2, resolution: UVec2) -> Vec2 {
+    ndc_to_metric(pixel_to_ndc(pixel, resolution), size)
+}
+
+pub fn metric_to_ndc(metric: Vec2, size: Vec2) -> Vec2 {
+    metric / (size / 2.)
+}
+
+pub fn ndc_to_metric(ndc: Vec2, size: Vec2) -> Vec2 {
+    ndc * (size / 2.)
+}
+
+pub fn ndc_to_pixel(ndc: Vec2, resolution: UVec2) -> Vec2 {
+    // Flip Y, then [-1, 1] -> [0, 1]
+    let unit = (Vec2::new(ndc.x, -ndc.y) + 1.) / 2.;
+
+    unit * resolution.as_vec2() - 0.5
+}
+
+pub fn pixel_to_ndc(pixel: Vec2, resolution: UVec2) -> Vec2 {
+    let unit = (pixel + 0.5) / resolution.as_vec2();
+    let ndc = unit * 2. - 1.;
+
+    Vec2::new(ndc.x, -ndc.y)
+}
@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{inspector_options::ReflectInspectorOptions, InspectorOptions};
+
+/// Lens distortion, applied to normalized image coordinates (before K)
+pub struct DistortionPlugin;
+
+impl Plugin for DistortionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LensDistortion>()
+            .register_type::<LensDistortion>()
+            .register_type::<DistortionModel>();
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+pub enum DistortionModel {
+    #[default]
+    None,
+
+    /// Radial (k1, k2, k3) and tangential (p1, p2) distortion
+    BrownConrady,
+
+    /// Equidistant fisheye, using k1 through k4 on the angle of incidence
+    Fisheye,
+}
+
+#[derive(Debug, Default, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct LensDistortion {
+    pub model: DistortionModel,
+
+    #[inspector(speed = 0.01)]
+    pub k1: f32,
+    #[inspector(speed = 0.01)]
+    pub k2: f32,
+    #[inspector(speed = 0.01)]
+    pub k3: f32,
+    /// Only used by [`DistortionModel::Fisheye`]
+    #[inspector(speed = 0.01)]
+    pub k4: f32,
+
+    /// Only used by [`DistortionModel::BrownConrady`]
+    #[inspector(speed = 0.001)]
+    pub p1: f32,
+    /// Only used by [`DistortionModel::BrownConrady`]
+    #[inspector(speed = 0.001)]
+    pub p2: f32,
+}
+
+/// Iterations used when inverting the distortion
+const UNDISTORT_ITERATIONS: usize = 20;
+
+impl LensDistortion {
+    pub fn is_identity(&self) -> bool {
+        self.model == DistortionModel::None
+    }
+
+    /// Ideal normalized image coordinates to distorted ones
+    pub fn distort(&self, ideal: Vec2) -> Vec2 {
+        match self.model {
+            DistortionModel::None => ideal,
+            DistortionModel::BrownConrady => {
+                let Vec2 { x, y } = ideal;
+                let r2 = ideal.length_squared();
+
+                ideal * self.radial(r2)
+                    + Vec2::new(
+                        2. * self.p1 * x * y + self.p2 * (r2 + 2. * x * x),
+                        self.p1 * (r2 + 2. * y * y) + 2. * self.p2 * x * y,
+                    )
+            }
+            DistortionModel::Fisheye => {
+                let r = ideal.length();
+                if r < f32::EPSILON {
+                    return ideal;
+                }
+
+                ideal * (self.fisheye_theta_d(r.atan()) / r)
+            }
+        }
+    }
+
+    /// Distorted normalized image coordinates to ideal ones, i.e. the inverse of [`Self::distort`].
+    ///
+    /// This is solved iteratively, so it is only exact up to a small residual,
+    /// and not at all for distortions strong enough to fold the image over itself.
+    pub fn undistort(&self, distorted: Vec2) -> Vec2 {
+        match self.model {
+            DistortionModel::None => distorted,
+            DistortionModel::BrownConrady => {
+                // Fixed point iteration, same as OpenCV's undistortPoints
+                let mut ideal = distorted;
+                for _ in 0..UNDISTORT_ITERATIONS {
+                    let Vec2 { x, y } = ideal;
+                    let r2 = ideal.length_squared();
+                    let tangential = Vec2::new(
+                        2. * self.p1 * x * y + self.p2 * (r2 + 2. * x * x),
+                        self.p1 * (r2 + 2. * y * y) + 2. * self.p2 * x * y,
+                    );
+
+                    ideal = (distorted - tangential) / self.radial(r2);
+                }
+                ideal
+            }
+            DistortionModel::Fisheye => {
+                let theta_d = distorted.length();
+                if theta_d < f32::EPSILON {
+                    return distorted;
+                }
+
+                // Newton's method on theta_d(theta) = theta_d
+                let mut theta = theta_d;
+                for _ in 0..UNDISTORT_ITERATIONS {
+                    let t2 = theta * theta;
+                    let derivative = 1.
+                        + 3. * self.k1 * t2
+                        + 5. * self.k2 * t2 * t2
+                        + 7. * self.k3 * t2 * t2 * t2
+                        + 9. * self.k4 * t2 * t2 * t2 * t2;
+
+                    theta -= (self.fisheye_theta_d(theta) - theta_d) / derivative;
+                }
+
+                distorted * (theta.tan() / theta_d)
+            }
+        }
+    }
+
+    fn radial(&self, r2: f32) -> f32 {
+        1. + self.k1 * r2 + self.k2 * r2 * r2 + self.k3 * r2 * r2 * r2
+    }
+
+    fn fisheye_theta_d(&self, theta: f32) -> f32 {
+        let t2 = theta * theta;
+
+        theta
+            * (1.
+                + self.k1 * t2
+                + self.k2 * t2 * t2
+                + self.k3 * t2 * t2 * t2
+                + self.k4 * t2 * t2 * t2 * t2)
+    }
+}
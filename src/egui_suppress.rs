@@ -1,9 +1,15 @@
 use bevy::prelude::*;
 use bevy_editor_cam::controller::component::EditorCam;
 use bevy_inspector_egui::bevy_egui;
+use bevy_mod_picking::focus::PickingInteraction;
 
 pub struct EguiSupressPlugin;
 
+/// Entities with this (and [`PickingInteraction`]) suppress camera motion while pressed,
+/// e.g. so that they can be dragged around
+#[derive(Debug, Component)]
+pub struct SuppressCameraWhilePressed;
+
 impl Plugin for EguiSupressPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
@@ -17,6 +23,7 @@ fn suppress(
     mut contexts: bevy_egui::EguiContexts,
     mut editor_cams: Query<&mut EditorCam>,
     transform_gizmos: Query<&transform_gizmo_bevy::GizmoTarget>,
+    pressables: Query<&PickingInteraction, With<SuppressCameraWhilePressed>>,
 ) {
     let ctx = contexts.ctx_mut();
 
@@ -30,6 +37,11 @@ fn suppress(
         .iter()
         .any(|g| g.is_active() || g.is_focused());
 
+    // Same for things being dragged
+    should_suppress |= pressables
+        .iter()
+        .any(|interaction| *interaction == PickingInteraction::Pressed);
+
     let enabled = !should_suppress;
 
     for mut cam in &mut editor_cams {
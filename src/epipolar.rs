@@ -0,0 +1,346 @@
+use bevy::{color::palettes, prelude::*};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use bevy_mod_picking::events::{Move, Out, Pointer};
+
+use crate::{
+    camera::{CameraIntrinsics, CameraProjection, ProjectionModel},
+    material_mesh_cache::MeshMaterialCache,
+    projection_matrix::matrix_label,
+    second_camera::{SecondCamera, SecondImagePlane, SecondRig},
+    ImagePlane, ImagePlanes, ImagePoint, ImagePoints, ImageSize, MainImagePlane, ParentTransforms,
+    PointMaterial, SensorPosition,
+};
+
+/// The essential and fundamental matrices between the main and the second camera, with their
+/// epipoles and epipolar lines
+pub struct EpipolarPlugin;
+
+impl Plugin for EpipolarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EpipolarSettings>()
+            .register_type::<EpipolarSettings>()
+            .init_resource::<EpipolarHover>()
+            .add_systems(
+                Update,
+                (track_epipolar_hover, ui_epipolar, gizmo_epipolar_geometry).chain(),
+            );
+    }
+}
+
+#[derive(Debug, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct EpipolarSettings {
+    show_epipoles: bool,
+    /// The epipolar line of each image point in the second camera
+    show_point_lines: bool,
+    /// The epipolar plane through the baseline and the position under the pointer
+    show_hover_plane: bool,
+}
+
+impl Default for EpipolarSettings {
+    fn default() -> Self {
+        Self {
+            show_epipoles: true,
+            show_point_lines: true,
+            show_hover_plane: true,
+        }
+    }
+}
+
+/// One of the two cameras
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Main,
+    Second,
+}
+
+impl View {
+    pub fn other(self) -> Self {
+        match self {
+            Self::Main => Self::Second,
+            Self::Second => Self::Main,
+        }
+    }
+}
+
+/// The cross product as a matrix, i.e. `skew(a) * b == a.cross(b)`
+pub fn skew(v: Vec3) -> Mat3 {
+    Mat3::from_cols(
+        Vec3::new(0.0, v.z, -v.y),
+        Vec3::new(-v.z, 0.0, v.x),
+        Vec3::new(v.y, -v.x, 0.0),
+    )
+}
+
+/// The epipolar geometry of the main camera (x₁) and the second camera (x₂), both with
+/// intrinsics K:
+///
+/// ```text
+/// E = [t]ₓ R          x₂ᵀ E x₁ = 0 for normalized image coordinates
+/// F = K⁻ᵀ E K⁻¹       x₂ᵀ F x₁ = 0 for sensor positions
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwoViewGeometry {
+    pub essential: Mat3,
+    pub fundamental: Mat3,
+
+    /// Where the main camera sees the second optical centre, as a homogeneous sensor position
+    pub main_epipole: Vec3,
+    /// Where the second camera sees the main optical centre, as a homogeneous sensor position
+    pub second_epipole: Vec3,
+}
+
+impl TwoViewGeometry {
+    /// `None` without a baseline, as then every pair of points is consistent
+    pub fn new(k: Mat3, second: &SecondCamera) -> Option<Self> {
+        let t = second.translation();
+        if t.length() < f32::EPSILON {
+            return None;
+        }
+
+        let essential = skew(t) * second.rotation_matrix();
+        let k_inv = k.inverse();
+
+        Some(Self {
+            essential,
+            fundamental: k_inv.transpose() * essential * k_inv,
+            main_epipole: k * second.baseline,
+            second_epipole: k * t,
+        })
+    }
+
+    pub fn epipole(&self, view: View) -> Vec3 {
+        match view {
+            View::Main => self.main_epipole,
+            View::Second => self.second_epipole,
+        }
+    }
+
+    /// The epipolar line in the other view of a sensor position in this view, as (a, b, c) with
+    /// ax + by + c = 0 in sensor coordinates
+    pub fn epipolar_line(&self, view: View, sensor: Vec2) -> Vec3 {
+        match view {
+            View::Main => self.fundamental * sensor.extend(1.0),
+            View::Second => self.fundamental.transpose() * sensor.extend(1.0),
+        }
+    }
+}
+
+/// The part of the line ax + by + c = 0 which is on the sensor, if any
+pub fn clip_to_sensor(line: Vec3, size: Vec2) -> Option<(Vec2, Vec2)> {
+    const TOLERANCE: f32 = 1e-5;
+    let half = size / 2.;
+    let mut hits = Vec::new();
+
+    if line.y.abs() > f32::EPSILON {
+        for x in [-half.x, half.x] {
+            let y = -(line.x * x + line.z) / line.y;
+            if y.abs() <= half.y + TOLERANCE {
+                hits.push(Vec2::new(x, y));
+            }
+        }
+    }
+    if line.x.abs() > f32::EPSILON {
+        for y in [-half.y, half.y] {
+            let x = -(line.y * y + line.z) / line.x;
+            if x.abs() <= half.x + TOLERANCE {
+                hits.push(Vec2::new(x, y));
+            }
+        }
+    }
+
+    // Through a corner, the same point is hit twice
+    let start = *hits.first()?;
+    let end = hits
+        .into_iter()
+        .max_by(|a, b| a.distance(start).total_cmp(&b.distance(start)))?;
+
+    (end.distance(start) > TOLERANCE).then_some((start, end))
+}
+
+/// The position under the pointer on the main image plane of either camera
+#[derive(Debug, Default, Resource)]
+struct EpipolarHover(Option<(View, Vec2)>);
+
+fn track_epipolar_hover(
+    mut hover: ResMut<EpipolarHover>,
+    mut moves: EventReader<Pointer<Move>>,
+    mut outs: EventReader<Pointer<Out>>,
+    main_plane: Query<&GlobalTransform, (With<ImagePlane>, With<MainImagePlane>)>,
+    second_planes: Query<(&SecondImagePlane, &GlobalTransform)>,
+) {
+    let view_of = |entity: Entity| {
+        if let Ok(transform) = main_plane.get(entity) {
+            return Some((View::Main, transform));
+        }
+        match second_planes.get(entity) {
+            Ok((SecondImagePlane { index: 0 }, transform)) => Some((View::Second, transform)),
+            _ => None,
+        }
+    };
+
+    for event in moves.read() {
+        let (Some((view, plane)), Some(position)) = (view_of(event.target), event.hit.position)
+        else {
+            continue;
+        };
+
+        // The plane meshes are in normalized image coordinates, see `sync_image_planes`
+        let normalized = plane.affine().inverse().transform_point3(position).xy();
+        hover.0 = Some((view, normalized));
+    }
+
+    for event in outs.read() {
+        if view_of(event.target).is_some() {
+            hover.0 = None;
+        }
+    }
+}
+
+fn ui_epipolar(
+    mut contexts: EguiContexts,
+    second: Res<SecondCamera>,
+    intrinsics: Res<CameraIntrinsics>,
+    projection: Res<CameraProjection>,
+) {
+    egui::Window::new("Epipolar geometry")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if !second.enabled {
+                ui.label("Enable the second camera to see its epipolar geometry");
+                return;
+            }
+            if projection.model != ProjectionModel::Perspective {
+                ui.label("Only the perspective projection is covered here");
+                return;
+            }
+            let Some(geometry) = TwoViewGeometry::new(intrinsics.matrix(), &second) else {
+                ui.label("Without a baseline, there is no epipolar geometry");
+                return;
+            };
+
+            ui.label("E = [t]ₓ R");
+            matrix_label(ui, geometry.essential);
+            ui.label("F = K⁻ᵀ E K⁻¹");
+            matrix_label(ui, geometry.fundamental);
+            ui.separator();
+
+            for (name, view) in [("main", View::Main), ("second", View::Second)] {
+                let epipole = geometry.epipole(view);
+                ui.label(if epipole.z.abs() > f32::EPSILON {
+                    let e = epipole.xy() / epipole.z;
+                    format!("{name} epipole: ({:.3}, {:.3})", e.x, e.y)
+                } else {
+                    let direction = epipole.xy().normalize_or_zero();
+                    format!(
+                        "{name} epipole: at infinity, towards ({:.3}, {:.3})",
+                        direction.x, direction.y
+                    )
+                });
+            }
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn gizmo_epipolar_geometry(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    settings: Res<EpipolarSettings>,
+    hover: Res<EpipolarHover>,
+    intrinsics: Res<CameraIntrinsics>,
+    projection: Res<CameraProjection>,
+    size: Res<ImageSize>,
+    planes: Res<ImagePlanes>,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    second: SecondRig,
+    points: Query<(&SensorPosition, &PointMaterial, &InheritedVisibility), With<ImagePoint>>,
+) {
+    if projection.model != ProjectionModel::Perspective {
+        return;
+    }
+    let (Some(main_rig), Some(second_rig)) = (parents.rig(), second.transform()) else {
+        return;
+    };
+    let Some(geometry) = TwoViewGeometry::new(intrinsics.matrix(), &second.settings) else {
+        return;
+    };
+
+    let rig = |view: View| match view {
+        View::Main => main_rig,
+        View::Second => second_rig,
+    };
+    // Both cameras have their main image plane at the same depth
+    let depth = planes.main_depth();
+    let on_plane = |view: View, sensor: Vec2, depth: f32| {
+        rig(view).transform_point(intrinsics.back_project(sensor, depth))
+    };
+
+    if settings.show_epipoles {
+        for view in [View::Main, View::Second] {
+            let epipole = geometry.epipole(view);
+            if epipole.z.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            gizmos.sphere(
+                on_plane(view, epipole.xy() / epipole.z, depth),
+                Quat::default(),
+                point_settings.point_size / 2.,
+                palettes::tailwind::SKY_400,
+            );
+        }
+    }
+
+    if settings.show_point_lines {
+        for (sensor_position, material, visibility) in &points {
+            if !visibility.get() {
+                continue;
+            }
+            let line = geometry.epipolar_line(View::Main, **sensor_position);
+            let Some((start, end)) = clip_to_sensor(line, **size) else {
+                continue;
+            };
+
+            gizmos.line(
+                on_plane(View::Second, start, depth),
+                on_plane(View::Second, end, depth),
+                cache.color(**material),
+            );
+        }
+    }
+
+    let Some((view, normalized)) = hover.0 else {
+        return;
+    };
+    let sensor = intrinsics.denormalize(normalized);
+    let other = view.other();
+    let Some((start, end)) = clip_to_sensor(geometry.epipolar_line(view, sensor), **size) else {
+        return;
+    };
+    let color = palettes::basic::WHITE;
+
+    gizmos.line(
+        on_plane(other, start, depth),
+        on_plane(other, end, depth),
+        color,
+    );
+
+    if settings.show_hover_plane {
+        // The ray through the hovered position and the rays through the ends of its epipolar line
+        // all lie in the epipolar plane, which contains the baseline
+        let far = planes.far_depth();
+        let centres = [view, other].map(|view| rig(view).translation());
+        let color = color.with_alpha(0.3);
+
+        gizmos.line(centres[0], centres[1], color);
+        gizmos.line(centres[0], on_plane(view, sensor, far), color);
+        gizmos.line(centres[1], on_plane(other, start, far), color);
+        gizmos.line(centres[1], on_plane(other, end, far), color);
+        gizmos.line(
+            on_plane(other, start, far),
+            on_plane(other, end, far),
+            color,
+        );
+    }
+}
@@ -1,17 +1,39 @@
-use bevy::prelude::*;
+use bevy::{color::palettes, prelude::*};
 
 use crate::{
-    material_mesh_cache::MeshMaterialCache, ImagePlane, ImagePlanes, ImagePoint, ImagePointIndex,
-    ImagePoints, MainImagePlane, MoveOverFirstPlaneEvent, SecondaryCamera,
+    camera::{CameraModel, CameraProjection},
+    material_mesh_cache::MeshMaterialCache,
+    world_points::{project_world_points, WorldPoint, WorldPointProjection},
+    FirstPlaneHover, HoverPosition, ImagePlane, ImagePlanes, ImagePoint, ImagePoints, ImageSize,
+    MainImagePlane, MoveOutFirstPlaneEvent, MoveOverFirstPlaneEvent, ParentTransforms,
+    PointMaterial, SecondaryCamera, SensorPosition,
 };
 
 pub struct GizmosPlugin;
 
-#[derive(Debug, Resource, Default, Reflect)]
+#[derive(Debug, Resource, Reflect)]
 #[reflect(Resource)]
 pub struct GizmoSettings {
     show_world_axes: bool,
+    /// The world axes as seen on the main image plane
+    show_projected_world_axes: bool,
     show_point_rays: bool,
+    /// From the optical centre to each world point
+    show_world_point_rays: bool,
+    /// How the lens distortion warps a regular grid on the main image plane
+    show_distortion_grid: bool,
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            show_world_axes: false,
+            show_projected_world_axes: false,
+            show_point_rays: false,
+            show_world_point_rays: true,
+            show_distortion_grid: false,
+        }
+    }
 }
 
 impl Plugin for GizmosPlugin {
@@ -20,14 +42,65 @@ impl Plugin for GizmosPlugin {
             .init_resource::<GizmoSettings>()
             .add_systems(
                 Update,
-                (gizmo_world_axes, gizmo_point_rays, gizmo_1st_image_plane),
+                (
+                    gizmo_world_axes,
+                    gizmo_projected_world_axes,
+                    gizmo_point_rays,
+                    gizmo_1st_image_plane,
+                    gizmo_world_point_projections.after(project_world_points),
+                    gizmo_distorted_points,
+                    gizmo_distortion_grid,
+                ),
             );
     }
 }
 
-fn gizmo_world_axes(mut gizmos: Gizmos, settings: Res<GizmoSettings>) {
-    if settings.show_world_axes {
-        gizmos.axes(Transform::default(), 1.0);
+fn gizmo_world_axes(mut gizmos: Gizmos, settings: Res<GizmoSettings>, parents: ParentTransforms) {
+    if !settings.show_world_axes {
+        return;
+    }
+    let Some(world) = parents.world() else {
+        return;
+    };
+
+    gizmos.axes(*world, 1.0);
+}
+
+fn gizmo_projected_world_axes(
+    mut gizmos: Gizmos,
+    settings: Res<GizmoSettings>,
+    parents: ParentTransforms,
+    projection: Res<CameraProjection>,
+    planes: Res<ImagePlanes>,
+) {
+    if !settings.show_projected_world_axes {
+        return;
+    }
+    let (Some(world), Some(rig)) = (parents.world(), parents.rig()) else {
+        return;
+    };
+
+    let world_to_camera = rig.affine().inverse() * world.affine();
+    let depth = planes.main_depth();
+
+    // Onto the main image plane in camera space, then back into world space for drawing
+    let project = |point: Vec3| {
+        let normalized = projection.project(world_to_camera.transform_point3(point))?;
+        Some(rig.transform_point(projection.point_on_plane(normalized, depth)))
+    };
+
+    let Some(origin) = project(Vec3::ZERO) else {
+        return;
+    };
+
+    for (axis, color) in [
+        (Vec3::X, palettes::basic::RED),
+        (Vec3::Y, palettes::basic::GREEN),
+        (Vec3::Z, palettes::basic::BLUE),
+    ] {
+        if let Some(end) = project(axis) {
+            gizmos.line(origin, end, color);
+        }
     }
 }
 
@@ -36,27 +109,42 @@ fn gizmo_point_rays(
     mut cache: MeshMaterialCache,
     settings: Res<GizmoSettings>,
     planes: Res<ImagePlanes>,
-    points: Query<(&Transform, &ImagePointIndex), With<ImagePoint>>,
+    parents: ParentTransforms,
+    camera: CameraModel,
+    points: Query<(&SensorPosition, &PointMaterial, &InheritedVisibility), With<ImagePoint>>,
 ) {
-    let num_planes = planes.num_planes as f32;
-    if settings.show_point_rays {
-        for (transform, ImagePointIndex { index }) in &points {
-            gizmos.line(
-                transform.translation,
-                transform.translation * num_planes,
-                cache.color(*index),
-            );
+    if !settings.show_point_rays {
+        return;
+    }
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+    let depths = planes.depths();
+    let (Some(&first), Some(&last)) = (depths.first(), depths.last()) else {
+        return;
+    };
+
+    for (sensor_position, material, visibility) in &points {
+        // Hidden along with its group, see [`crate::point_labels::PointGroups`]
+        if !visibility.get() {
+            continue;
         }
+        gizmos.line(
+            rig.transform_point(camera.back_project(**sensor_position, first)),
+            rig.transform_point(camera.back_project(**sensor_position, last)),
+            cache.color(**material),
+        );
     }
 }
 
 fn gizmo_1st_image_plane(
-    mut stored_pos: Local<Option<Vec3>>,
+    mut hover: ResMut<FirstPlaneHover>,
     mut gizmos: Gizmos,
     point_settings: Res<ImagePoints>,
     mut over_events: EventReader<MoveOverFirstPlaneEvent>,
+    mut out_events: EventReader<MoveOutFirstPlaneEvent>,
     secondary_camera: Query<&Camera, With<SecondaryCamera>>,
-    main_image_plane: Query<Entity, (With<ImagePlane>, With<MainImagePlane>)>,
+    main_image_plane: Query<&GlobalTransform, (With<ImagePlane>, With<MainImagePlane>)>,
 ) {
     for e in over_events.read() {
         // We only care about interactions from this camera
@@ -65,31 +153,172 @@ fn gizmo_1st_image_plane(
         }
 
         // Only care about this exact plane
-        if main_image_plane.get(e.data.target).is_err() {
+        let Ok(plane) = main_image_plane.get(e.data.target) else {
             continue;
-        }
+        };
 
         let Some(pos) = e.hit.position else {
             warn!("unexpected positionless main image plane hit");
             continue;
         };
 
-        // All hit positions should be at image plane Z=1.0
-        if (1.0 - pos.z).abs() > 0.001 {
+        // All hit positions should be on the plane itself, which lies in its local XY
+        let local = plane.affine().inverse().transform_point3(pos);
+        if local.z.abs() > 0.001 {
             warn!("unexpected main image plane world position: {pos:?}");
             continue;
         }
 
-        debug!("hit image plane at: {:?}", pos.xy());
-        *stored_pos = Some(pos);
+        debug!("hit image plane at: {:?}", local.xy());
+        hover.0 = Some(HoverPosition {
+            world: pos,
+            normalized: local.xy(),
+        });
     }
 
-    if let Some(pos) = *stored_pos {
+    for e in out_events.read() {
+        if main_image_plane.contains(e.data.target) {
+            hover.0 = None;
+        }
+    }
+
+    if let Some(HoverPosition { world, .. }) = **hover {
         gizmos.sphere(
-            pos,
+            world,
             Quat::default(),
             point_settings.point_size / 2.,
             Color::WHITE.with_alpha(0.15),
         );
     }
 }
+
+#[allow(clippy::too_many_arguments)]
+fn gizmo_world_point_projections(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    settings: Res<GizmoSettings>,
+    point_settings: Res<ImagePoints>,
+    planes: Res<ImagePlanes>,
+    parents: ParentTransforms,
+    projection: Res<CameraProjection>,
+    points: Query<(&GlobalTransform, &WorldPoint, &WorldPointProjection)>,
+) {
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+
+    for (transform, WorldPoint { index }, normalized) in &points {
+        let Some(normalized) = **normalized else {
+            continue;
+        };
+        let color = cache.color(*index);
+
+        if settings.show_world_point_rays {
+            // Under perspective, this goes through the optical centre,
+            // continuing onto the planes behind it if there are any
+            let start = planes.depths().into_iter().fold(0.0, f32::min);
+            gizmos.line(
+                rig.transform_point(projection.point_on_plane(normalized, start)),
+                transform.translation(),
+                color,
+            );
+        }
+
+        for depth in planes.depths() {
+            gizmos.sphere(
+                rig.transform_point(projection.point_on_plane(normalized, depth)),
+                Quat::default(),
+                point_settings.point_size / 2.,
+                color,
+            );
+        }
+    }
+}
+
+fn gizmo_distorted_points(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    camera: CameraModel,
+    point_settings: Res<ImagePoints>,
+    planes: Res<ImagePlanes>,
+    parents: ParentTransforms,
+    points: Query<(&SensorPosition, &PointMaterial, &InheritedVisibility), With<ImagePoint>>,
+) {
+    if camera.distortion.is_identity() {
+        return;
+    }
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+
+    for (sensor_position, material, visibility) in &points {
+        if !visibility.get() {
+            continue;
+        }
+        let ideal = camera.intrinsics.normalize(**sensor_position);
+        let distorted = camera.distortion.distort(ideal);
+        let color = cache.color(**material);
+
+        for z in planes.depths() {
+            let ideal = rig.transform_point(camera.projection.point_on_plane(ideal, z));
+            let distorted = rig.transform_point(camera.projection.point_on_plane(distorted, z));
+
+            gizmos.sphere(
+                distorted,
+                Quat::default(),
+                point_settings.point_size / 2.,
+                color,
+            );
+            gizmos.line(ideal, distorted, color);
+        }
+    }
+}
+
+fn gizmo_distortion_grid(
+    mut gizmos: Gizmos,
+    settings: Res<GizmoSettings>,
+    camera: CameraModel,
+    size: Res<ImageSize>,
+    planes: Res<ImagePlanes>,
+    parents: ParentTransforms,
+) {
+    const LINES: usize = 10;
+    const SAMPLES: usize = 32;
+
+    if !settings.show_distortion_grid {
+        return;
+    }
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+
+    let half = **size / 2.;
+    let depth = planes.main_depth();
+    let to_plane = |metric: Vec2| {
+        let distorted = camera
+            .distortion
+            .distort(camera.intrinsics.normalize(metric));
+        rig.transform_point(camera.projection.point_on_plane(distorted, depth))
+    };
+    let color = palettes::tailwind::GREEN_300;
+
+    for line in 0..=LINES {
+        let t = line as f32 / LINES as f32;
+
+        // Vertical, then horizontal
+        gizmos.linestrip(
+            (0..=SAMPLES).map(|sample| {
+                let s = sample as f32 / SAMPLES as f32;
+                to_plane(Vec2::new(-half.x, -half.y) + Vec2::new(t, s) * **size)
+            }),
+            color,
+        );
+        gizmos.linestrip(
+            (0..=SAMPLES).map(|sample| {
+                let s = sample as f32 / SAMPLES as f32;
+                to_plane(Vec2::new(-half.x, -half.y) + Vec2::new(s, t) * **size)
+            }),
+            color,
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use bevy_mod_picking::{
+    events::{Click, Pointer},
+    pointer::PointerButton,
+};
+
+use crate::{
+    camera::CameraModel, material_mesh_cache::MeshMaterialCache, ImagePlanes, ImagePoint,
+    ImagePointIndex, ImagePoints, ParentTransforms, PointGroup, PointLabel, PointMaterial,
+    RecordGroup, RecordLabel, SensorPosition, SubImagePoint,
+};
+
+/// Click an image point (or any of its sub-points) to inspect its homogeneous coordinates,
+/// and to give it a label or group
+pub struct HomogeneousPlugin;
+
+impl Plugin for HomogeneousPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HomogeneousInspector>().add_systems(
+            Update,
+            (
+                select_image_point,
+                animate_division,
+                ui_homogeneous,
+                gizmo_division,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Debug, Resource)]
+struct HomogeneousInspector {
+    /// The [`ImagePoint`] being inspected
+    selected: Option<Entity>,
+
+    /// The homogeneous coordinate currently shown in the division animation
+    w: f32,
+    playing: bool,
+}
+
+impl Default for HomogeneousInspector {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            w: 1.0,
+            playing: false,
+        }
+    }
+}
+
+/// How fast w moves towards ±1 while playing, per second
+const DIVISION_SPEED: f32 = 1.0;
+
+/// Where the division animation starts and ends.
+/// It slides from the furthest plane towards w=±1, depending on which side of the optical centre
+/// the planes are.
+fn division_range(planes: &ImagePlanes) -> (f32, f32) {
+    let far = planes.far_depth();
+    let end = if far < 0.0 { -1.0 } else { 1.0 };
+
+    (if far.abs() < 1.0 { end } else { far }, end)
+}
+
+fn select_image_point(
+    mut inspector: ResMut<HomogeneousInspector>,
+    mut clicks: EventReader<Pointer<Click>>,
+    planes: Res<ImagePlanes>,
+    points: Query<(Entity, &ImagePointIndex), With<ImagePoint>>,
+    sub_points: Query<&ImagePointIndex, With<SubImagePoint>>,
+) {
+    for click in clicks.read() {
+        if click.event.button != PointerButton::Primary {
+            continue;
+        }
+
+        let selected = if points.contains(click.target) {
+            Some(click.target)
+        } else if let Ok(sub_point_index) = sub_points.get(click.target) {
+            points
+                .iter()
+                .find(|(_, index)| index.index == sub_point_index.index)
+                .map(|(entity, _)| entity)
+        } else {
+            None
+        };
+
+        if let Some(selected) = selected {
+            *inspector = HomogeneousInspector {
+                selected: Some(selected),
+                w: division_range(&planes).0,
+                playing: false,
+            };
+        }
+    }
+}
+
+fn animate_division(
+    time: Res<Time>,
+    planes: Res<ImagePlanes>,
+    mut inspector: ResMut<HomogeneousInspector>,
+) {
+    if !inspector.playing {
+        return;
+    }
+
+    let (_, end) = division_range(&planes);
+    inspector.w -= end * time.delta_seconds() * DIVISION_SPEED;
+    if inspector.w.abs() <= 1.0 {
+        inspector.w = end;
+        inspector.playing = false;
+    }
+}
+
+fn ui_homogeneous(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut inspector: ResMut<HomogeneousInspector>,
+    camera: CameraModel,
+    planes: Res<ImagePlanes>,
+    points: Query<(&SensorPosition, &ImagePointIndex), With<ImagePoint>>,
+    annotations: Query<(Option<&PointLabel>, Option<&PointGroup>)>,
+) {
+    let Some(selected) = inspector.selected else {
+        return;
+    };
+    let (Ok((sensor_position, ImagePointIndex { index })), Ok((label, group))) =
+        (points.get(selected), annotations.get(selected))
+    else {
+        // The point is gone, e.g. because there are fewer points now
+        inspector.selected = None;
+        return;
+    };
+
+    let normalized = camera.intrinsics.normalize(**sensor_position);
+    let (start, end) = division_range(&planes);
+    let mut open = true;
+
+    egui::Window::new("Homogeneous coordinates")
+        .open(&mut open)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!("point {index}"));
+            let mut label = label.map(|label| label.0.clone()).unwrap_or_default();
+            let mut group = group.map(|group| group.0.clone()).unwrap_or_default();
+            egui::Grid::new("point annotations").show(ui, |ui| {
+                ui.label("label");
+                if ui.text_edit_singleline(&mut label).changed() {
+                    if label.is_empty() {
+                        commands.entity(selected).remove::<PointLabel>();
+                    } else {
+                        commands.entity(selected).insert(PointLabel(label));
+                    }
+                    // Typed in now, so resampling should leave it be
+                    commands.entity(selected).remove::<RecordLabel>();
+                }
+                ui.end_row();
+
+                ui.label("group");
+                if ui.text_edit_singleline(&mut group).changed() {
+                    if group.is_empty() {
+                        commands.entity(selected).remove::<PointGroup>();
+                    } else {
+                        commands.entity(selected).insert(PointGroup(group));
+                    }
+                    commands.entity(selected).remove::<RecordGroup>();
+                }
+                ui.end_row();
+            });
+            ui.separator();
+
+            ui.label(format!(
+                "representative (x, y, 1): ({:.3}, {:.3}, 1)",
+                normalized.x, normalized.y
+            ));
+            ui.separator();
+
+            egui::Grid::new("homogeneous per plane")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("plane");
+                    ui.label("(x·z, y·z, z)");
+                    ui.end_row();
+
+                    for (plane_index, depth) in planes.depths().into_iter().enumerate() {
+                        let h = normalized.extend(1.0) * depth;
+
+                        ui.label(format!("{}", plane_index + 1));
+                        ui.label(format!("({:.3}, {:.3}, {:.3})", h.x, h.y, h.z));
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+
+            ui.label("Perspective division");
+            let w = inspector.w;
+            let h = normalized.extend(1.0) * w;
+            ui.label(format!("(X, Y, W) = ({:.3}, {:.3}, {:.3})", h.x, h.y, h.z));
+            ui.label(format!(
+                "(X/W, Y/W, W/W) = ({:.3}, {:.3}, {:.3})",
+                h.x / w,
+                h.y / w,
+                h.z / w
+            ));
+
+            ui.horizontal(|ui| {
+                let play_label = if inspector.playing { "Pause" } else { "Play" };
+                if ui.button(play_label).clicked() {
+                    if !inspector.playing && inspector.w == end {
+                        inspector.w = start;
+                    }
+                    inspector.playing = !inspector.playing;
+                }
+                ui.add(egui::Slider::new(&mut inspector.w, end..=start).text("W"));
+            });
+        });
+
+    if !open {
+        inspector.selected = None;
+    }
+}
+
+fn gizmo_division(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    inspector: Res<HomogeneousInspector>,
+    camera: CameraModel,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    points: Query<(&SensorPosition, &PointMaterial), With<ImagePoint>>,
+) {
+    let Some(Ok((sensor_position, material))) =
+        inspector.selected.map(|selected| points.get(selected))
+    else {
+        return;
+    };
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+
+    let color = cache.color(**material);
+    let on_ray = rig.transform_point(camera.back_project(**sensor_position, inspector.w));
+    let representative =
+        rig.transform_point(camera.back_project(**sensor_position, inspector.w.signum()));
+
+    gizmos.sphere(on_ray, Quat::default(), point_settings.point_size, color);
+    gizmos.line(representative, on_ray, Color::WHITE);
+}
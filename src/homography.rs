@@ -0,0 +1,489 @@
+use bevy::{
+    color::palettes,
+    math::{DMat3, DVec2, DVec3},
+    prelude::*,
+};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts, egui, inspector_options::ReflectInspectorOptions, InspectorOptions,
+};
+use thiserror::Error;
+
+use crate::{
+    camera::CameraModel, linalg, material_mesh_cache::MeshMaterialCache,
+    projection_matrix::matrix_label, sensor_corners, ImagePlanes, ImagePoint, ImagePoints,
+    ImageSize, ParentTransforms, PointMaterial, SensorPosition,
+};
+
+/// The homography from the main image plane onto another (possibly tilted) plane, estimated from
+/// where the rays through the image points cross both
+pub struct HomographyPlugin;
+
+impl Plugin for HomographyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HomographySettings>()
+            .register_type::<HomographySettings>()
+            .init_resource::<EstimatedHomography>()
+            .add_systems(
+                Update,
+                (estimate_plane_homography, ui_homography, gizmo_homography).chain(),
+            );
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum HomographyError {
+    #[error("at least 4 correspondences are needed, got {0}")]
+    TooFewCorrespondences(usize),
+
+    #[error("the correspondences contain non-finite values")]
+    NonFinite,
+
+    #[error("the points are collinear, so they cannot pin down a homography")]
+    Collinear,
+
+    #[error("the correspondences do not determine a unique homography")]
+    Degenerate,
+
+    #[error("the estimate is singular, so it is not a homography")]
+    Singular,
+}
+
+/// Moves the centroid to the origin and scales to an average distance of √2 from it,
+/// see Hartley & Zisserman (4.4.4)
+fn normalizing_transform(points: &[DVec2]) -> Result<DMat3, HomographyError> {
+    let centroid = points.iter().sum::<DVec2>() / points.len() as f64;
+    let mean_distance =
+        points.iter().map(|p| p.distance(centroid)).sum::<f64>() / points.len() as f64;
+    if mean_distance < 1e-12 {
+        return Err(HomographyError::Degenerate);
+    }
+    let scale = std::f64::consts::SQRT_2 / mean_distance;
+
+    Ok(DMat3::from_cols(
+        DVec3::new(scale, 0.0, 0.0),
+        DVec3::new(0.0, scale, 0.0),
+        (-scale * centroid).extend(1.0),
+    ))
+}
+
+/// Whether any three of four points are (nearly) on a line, or all of more points are.
+/// The points should be normalized, so the tolerance does not depend on their scale.
+fn is_collinear(points: &[DVec2]) -> bool {
+    const TOLERANCE: f64 = 1e-6;
+
+    if points.len() == 4 {
+        return (0..4).any(|skip| {
+            let [a, b, c] = [0, 1, 2].map(|i| points[if i < skip { i } else { i + 1 }]);
+            (b - a).perp_dot(c - a).abs() < TOLERANCE
+        });
+    }
+
+    // The spread is only along one direction when the covariance is rank deficient
+    let covariance: Vec<Vec<f64>> = (0..2)
+        .map(|i| {
+            (0..2)
+                .map(|j| points.iter().map(|p| p[i] * p[j]).sum())
+                .collect()
+        })
+        .collect();
+    let eigen = linalg::symmetric_eigen(&covariance);
+
+    eigen[0].0 < TOLERANCE * eigen[1].0.max(f64::EPSILON)
+}
+
+/// H such that `to ~ H from` for each pair, using the normalized DLT.
+/// Normalized such that its bottom right entry is 1 where possible.
+pub fn estimate_homography(pairs: &[(Vec2, Vec2)]) -> Result<Mat3, HomographyError> {
+    if pairs.len() < 4 {
+        return Err(HomographyError::TooFewCorrespondences(pairs.len()));
+    }
+    if pairs
+        .iter()
+        .any(|(from, to)| !from.is_finite() || !to.is_finite())
+    {
+        return Err(HomographyError::NonFinite);
+    }
+
+    let from: Vec<DVec2> = pairs.iter().map(|(from, _)| from.as_dvec2()).collect();
+    let to: Vec<DVec2> = pairs.iter().map(|(_, to)| to.as_dvec2()).collect();
+    let (t_from, t_to) = (normalizing_transform(&from)?, normalizing_transform(&to)?);
+    let normalize = |t: DMat3, points: &[DVec2]| -> Vec<DVec2> {
+        points.iter().map(|p| t.transform_point2(*p)).collect()
+    };
+    let (from, to) = (normalize(t_from, &from), normalize(t_to, &to));
+    if is_collinear(&from) || is_collinear(&to) {
+        return Err(HomographyError::Collinear);
+    }
+
+    let mut rows = Vec::new();
+    for (p, q) in from.iter().zip(&to) {
+        rows.push(vec![
+            0.0,
+            0.0,
+            0.0,
+            -p.x,
+            -p.y,
+            -1.0,
+            q.y * p.x,
+            q.y * p.y,
+            q.y,
+        ]);
+        rows.push(vec![
+            p.x,
+            p.y,
+            1.0,
+            0.0,
+            0.0,
+            0.0,
+            -q.x * p.x,
+            -q.x * p.y,
+            -q.x,
+        ]);
+    }
+
+    // With exactly 4 points both the smallest and second smallest eigenvalue are (close to) 0
+    // when the system is degenerate, instead of only the smallest
+    let eigen = linalg::symmetric_eigen(&linalg::normal_matrix(&rows));
+    let largest = eigen.last().map_or(0.0, |(value, _)| *value);
+    if eigen[1].0 <= 1e-12 * largest {
+        return Err(HomographyError::Degenerate);
+    }
+    let h = &eigen[0].1;
+
+    // Row major h into column major glam
+    let normalized = DMat3::from_cols(
+        DVec3::new(h[0], h[3], h[6]),
+        DVec3::new(h[1], h[4], h[7]),
+        DVec3::new(h[2], h[5], h[8]),
+    );
+    let mut h = t_to.inverse() * normalized * t_from;
+
+    let largest = h
+        .to_cols_array()
+        .iter()
+        .fold(0.0, |acc: f64, v| acc.max(v.abs()));
+    if h.determinant().abs() < 1e-9 * largest.powi(3) {
+        return Err(HomographyError::Singular);
+    }
+    if h.z_axis.z.abs() > 1e-12 * largest {
+        h /= h.z_axis.z;
+    }
+
+    Ok(h.as_mat3())
+}
+
+/// H applied to a point, `None` if it maps to infinity
+pub fn apply_homography(h: Mat3, point: Vec2) -> Option<Vec2> {
+    let mapped = h * point.extend(1.0);
+
+    (mapped.z.abs() > f32::EPSILON).then(|| mapped.xy() / mapped.z)
+}
+
+/// What kind of mapping H is, for a homography normalized like [`estimate_homography`] does
+fn describe_homography(h: Mat3) -> String {
+    const TOLERANCE: f32 = 1e-4;
+    // Entry (row, col), the matrices are column major
+    let at = |row: usize, col: usize| h.col(col)[row];
+    let is_zero = |row, col| at(row, col).abs() < TOLERANCE;
+
+    if !is_zero(2, 0) || !is_zero(2, 1) {
+        return "projective".to_string();
+    }
+    let is_scaling = is_zero(0, 1)
+        && is_zero(1, 0)
+        && is_zero(0, 2)
+        && is_zero(1, 2)
+        && (at(0, 0) - at(1, 1)).abs() < TOLERANCE;
+    if is_scaling {
+        return format!("a pure scaling by {:.3}", at(0, 0));
+    }
+
+    "affine".to_string()
+}
+
+/// The plane the main image plane is mapped onto.
+/// Its origin is on the optical axis, at `depth` in camera space.
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct HomographySettings {
+    enabled: bool,
+
+    depth: f32,
+
+    /// Euler angles (XYZ order, in degrees) of the plane. Without tilt it is fronto-parallel, like
+    /// the image planes.
+    tilt: Vec3,
+
+    /// Lines of the reference grid on the sensor, in each direction
+    #[inspector(min = 1)]
+    grid_lines: usize,
+}
+
+impl Default for HomographySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            depth: 3.0,
+            tilt: Vec3::new(0.0, 35.0, 0.0),
+            grid_lines: 8,
+        }
+    }
+}
+
+impl HomographySettings {
+    fn rotation(&self) -> Quat {
+        let r = self.tilt;
+        Quat::from_euler(
+            EulerRot::XYZ,
+            r.x.to_radians(),
+            r.y.to_radians(),
+            r.z.to_radians(),
+        )
+    }
+
+    /// Plane coordinates to camera space
+    fn plane_to_camera(&self) -> Transform {
+        Transform::from_xyz(0.0, 0.0, self.depth).with_rotation(self.rotation())
+    }
+}
+
+/// Where the ray through a sensor position crosses the target plane, in the coordinates of that
+/// plane. `None` if the ray runs parallel to the plane.
+fn onto_target_plane(
+    camera: &CameraModel,
+    settings: &HomographySettings,
+    sensor: Vec2,
+) -> Option<Vec2> {
+    let plane = settings.plane_to_camera();
+    let origin = camera.back_project(sensor, 0.0);
+    let direction = camera.back_project(sensor, 1.0) - origin;
+    let normal = plane.rotation * Vec3::Z;
+
+    let denominator = direction.dot(normal);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let hit = origin + direction * (plane.translation - origin).dot(normal) / denominator;
+
+    Some((plane.rotation.inverse() * (hit - plane.translation)).xy())
+}
+
+/// The homography from camera space XY on the main image plane onto the coordinates of the target
+/// plane, along with how many correspondences it was estimated from
+#[derive(Debug, Resource)]
+struct EstimatedHomography {
+    homography: Result<Mat3, HomographyError>,
+    correspondences: usize,
+    /// Root mean square distance between the warped points and where their rays actually cross
+    /// the target plane
+    rms_error: f32,
+}
+
+impl Default for EstimatedHomography {
+    fn default() -> Self {
+        Self {
+            homography: Err(HomographyError::TooFewCorrespondences(0)),
+            correspondences: 0,
+            rms_error: 0.0,
+        }
+    }
+}
+
+fn estimate_plane_homography(
+    mut estimated: ResMut<EstimatedHomography>,
+    settings: Res<HomographySettings>,
+    camera: CameraModel,
+    planes: Res<ImagePlanes>,
+    points: Query<&SensorPosition, With<ImagePoint>>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    let depth = planes.main_depth();
+
+    let pairs: Vec<(Vec2, Vec2)> = points
+        .iter()
+        .filter_map(|sensor| {
+            let from = camera.back_project(**sensor, depth).xy();
+            Some((from, onto_target_plane(&camera, &settings, **sensor)?))
+        })
+        .collect();
+    let homography = estimate_homography(&pairs);
+
+    let rms_error = match &homography {
+        Ok(h) => {
+            let squared: f32 = pairs
+                .iter()
+                .map(|(from, to)| {
+                    apply_homography(*h, *from).map_or(f32::INFINITY, |p| p.distance_squared(*to))
+                })
+                .sum();
+            (squared / pairs.len() as f32).sqrt()
+        }
+        Err(_) => 0.0,
+    };
+
+    *estimated = EstimatedHomography {
+        homography,
+        correspondences: pairs.len(),
+        rms_error,
+    };
+}
+
+fn ui_homography(
+    mut contexts: EguiContexts,
+    settings: Res<HomographySettings>,
+    estimated: Res<EstimatedHomography>,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    egui::Window::new("Homography")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("From the main image plane onto the target plane, using the image points");
+            ui.label(format!("{} correspondences", estimated.correspondences));
+            ui.separator();
+
+            match &estimated.homography {
+                Ok(h) => {
+                    ui.label("H");
+                    matrix_label(ui, *h);
+                    ui.label(format!("This is {}", describe_homography(*h)));
+                    ui.label(format!("RMS error: {:.2e}", estimated.rms_error));
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, e.to_string());
+                }
+            }
+        });
+}
+
+/// The target plane, with the image points and a grid over the sensor warped onto it
+#[allow(clippy::too_many_arguments)]
+fn gizmo_homography(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    settings: Res<HomographySettings>,
+    estimated: Res<EstimatedHomography>,
+    camera: CameraModel,
+    size: Res<ImageSize>,
+    planes: Res<ImagePlanes>,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    points: Query<(&SensorPosition, &PointMaterial, &InheritedVisibility), With<ImagePoint>>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+    let plane = rig
+        .compute_transform()
+        .mul_transform(settings.plane_to_camera());
+    let to_world = |p: Vec2| plane.transform_point(p.extend(0.0));
+
+    // Roughly the size of the sensor at that depth, to show where the plane is even without H
+    gizmos.rect(
+        plane.translation,
+        plane.rotation,
+        **size * settings.depth.abs(),
+        palettes::tailwind::SKY_300.with_alpha(0.3),
+    );
+
+    let Ok(h) = estimated.homography else {
+        return;
+    };
+    let depth = planes.main_depth();
+    let warp =
+        |sensor: Vec2| apply_homography(h, camera.back_project(sensor, depth).xy()).map(to_world);
+
+    for (sensor, material, visibility) in &points {
+        if !visibility.get() {
+            continue;
+        }
+        if let Some(position) = warp(**sensor) {
+            gizmos.sphere(
+                position,
+                Quat::default(),
+                point_settings.point_size / 2.,
+                cache.color(**material),
+            );
+        }
+    }
+
+    const SAMPLES: usize = 16;
+    let [bottom_left, _, top_right, _] = sensor_corners(&size);
+    let lines = settings.grid_lines.max(1);
+    let color = palettes::tailwind::SKY_400;
+
+    for line in 0..=lines {
+        let t = line as f32 / lines as f32;
+
+        // Vertical, then horizontal. Sampled even though lines stay straight, as they may cross
+        // the line at infinity.
+        for (start, direction) in [(Vec2::new(t, 0.0), Vec2::Y), (Vec2::new(0.0, t), Vec2::X)] {
+            gizmos.linestrip(
+                (0..=SAMPLES).filter_map(|sample| {
+                    let s = sample as f32 / SAMPLES as f32;
+                    warp(bottom_left + (start + direction * s) * (top_right - bottom_left))
+                }),
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: [Vec2; 4] = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+    ];
+
+    #[test]
+    fn recovers_known_homography() {
+        let h = Mat3::from_cols(
+            Vec3::new(1.2, 0.1, 0.05),
+            Vec3::new(-0.2, 0.9, -0.03),
+            Vec3::new(0.3, -0.1, 1.0),
+        );
+        let pairs: Vec<(Vec2, Vec2)> = SQUARE
+            .iter()
+            .map(|p| (*p, apply_homography(h, *p).unwrap()))
+            .collect();
+        let estimate = estimate_homography(&pairs).unwrap();
+
+        assert!(estimate.abs_diff_eq(h, 1e-4), "{estimate} != {h}");
+    }
+
+    #[test]
+    fn collinear_points_are_an_error() {
+        // The first three are on y = x
+        let from = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 1.0),
+        ];
+        let pairs: Vec<(Vec2, Vec2)> = from.iter().zip(SQUARE).map(|(a, b)| (*a, b)).collect();
+
+        assert_eq!(estimate_homography(&pairs), Err(HomographyError::Collinear));
+    }
+
+    #[test]
+    fn three_points_are_too_few() {
+        let pairs: Vec<(Vec2, Vec2)> = SQUARE[..3].iter().map(|p| (*p, *p)).collect();
+
+        assert_eq!(
+            estimate_homography(&pairs),
+            Err(HomographyError::TooFewCorrespondences(3))
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use bevy::{
+    color::palettes,
+    prelude::*,
+    render::{mesh::PrimitiveTopology, render_asset::RenderAssetUsages},
+};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use bevy_mod_picking::prelude::Pickable;
+
+use crate::{
+    camera::CameraModel,
+    material_mesh_cache::MeshMaterialCache,
+    point_editing::{detect_main_plane_clicks, MainPlaneClick},
+    should_remake, sync_sub_points, ImagePlanes, ImagePoints, MainPointsCommands, ParentTransforms,
+};
+
+/// Lines, polygons and conics on the main image plane, together with the surfaces they
+/// back-project to
+pub struct ImageShapesPlugin;
+
+impl Plugin for ImageShapesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ImageShapes>()
+            .register_type::<ImageShapes>()
+            .register_type::<ImageShape>()
+            .init_resource::<ShapeTool>()
+            .add_systems(
+                Update,
+                (
+                    (ui_image_shapes, place_shape_vertices)
+                        .chain()
+                        .after(detect_main_plane_clicks),
+                    back_projected_surfaces
+                        .after(sync_sub_points)
+                        .run_if(should_remake.or_else(resource_changed::<ImageShapes>)),
+                    gizmo_image_shapes,
+                ),
+            );
+    }
+}
+
+/// A shape on the sensor, in metric sensor coordinates
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum ImageShape {
+    Segment {
+        start: Vec2,
+        end: Vec2,
+    },
+    Polyline(Vec<Vec2>),
+    Polygon(Vec<Vec2>),
+    /// Rotated by `rotation` degrees counter-clockwise. Equal half sizes give a circle.
+    Ellipse {
+        centre: Vec2,
+        half_size: Vec2,
+        rotation: f32,
+    },
+}
+
+/// Samples along the boundary of an ellipse
+const ELLIPSE_SAMPLES: usize = 64;
+
+impl ImageShape {
+    fn kind(&self) -> ShapeKind {
+        match self {
+            Self::Segment { .. } => ShapeKind::Segment,
+            Self::Polyline(_) => ShapeKind::Polyline,
+            Self::Polygon(_) => ShapeKind::Polygon,
+            Self::Ellipse { .. } => ShapeKind::Ellipse,
+        }
+    }
+
+    /// Points along the shape, and whether the last point connects back to the first
+    pub fn outline(&self) -> (Vec<Vec2>, bool) {
+        match self {
+            Self::Segment { start, end } => (vec![*start, *end], false),
+            Self::Polyline(points) => (points.clone(), false),
+            Self::Polygon(points) => (points.clone(), true),
+            Self::Ellipse {
+                centre,
+                half_size,
+                rotation,
+            } => {
+                let rotation = Vec2::from_angle(rotation.to_radians());
+                let points = (0..ELLIPSE_SAMPLES)
+                    .map(|i| {
+                        let angle = std::f32::consts::TAU * i as f32 / ELLIPSE_SAMPLES as f32;
+                        *centre + rotation.rotate(Vec2::from_angle(angle) * *half_size)
+                    })
+                    .collect();
+                (points, true)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ImageShapes {
+    shapes: Vec<ImageShape>,
+
+    /// Draw the plane, pyramid or cone each shape back-projects to
+    show_surfaces: bool,
+}
+
+impl Default for ImageShapes {
+    fn default() -> Self {
+        Self {
+            shapes: vec![ImageShape::Segment {
+                start: Vec2::new(-0.4, -0.2),
+                end: Vec2::new(0.4, 0.25),
+            }],
+            show_surfaces: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShapeKind {
+    Segment,
+    Polyline,
+    Polygon,
+    Ellipse,
+}
+
+impl ShapeKind {
+    const ALL: [Self; 4] = [Self::Segment, Self::Polyline, Self::Polygon, Self::Ellipse];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Segment => "Segment",
+            Self::Polyline => "Polyline",
+            Self::Polygon => "Polygon",
+            Self::Ellipse => "Ellipse",
+        }
+    }
+
+    /// The shape made from the clicked vertices, if there are enough of them
+    fn build(self, vertices: &[Vec2]) -> Option<ImageShape> {
+        match (self, vertices) {
+            (Self::Segment, [start, end]) => Some(ImageShape::Segment {
+                start: *start,
+                end: *end,
+            }),
+            (Self::Polyline, vertices) if vertices.len() >= 2 => {
+                Some(ImageShape::Polyline(vertices.to_vec()))
+            }
+            (Self::Polygon, vertices) if vertices.len() >= 3 => {
+                Some(ImageShape::Polygon(vertices.to_vec()))
+            }
+            // The centre, then a corner of the bounding box
+            (Self::Ellipse, [centre, corner]) => Some(ImageShape::Ellipse {
+                centre: *centre,
+                half_size: (*corner - *centre).abs(),
+                rotation: 0.0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether the shape is done without the user saying so
+    fn is_complete(self, vertices: &[Vec2]) -> bool {
+        matches!(self, Self::Segment | Self::Ellipse) && vertices.len() == 2
+    }
+}
+
+/// The shape being placed by clicking on the main image plane, if any
+#[derive(Debug, Default, Resource)]
+pub struct ShapeTool {
+    kind: Option<ShapeKind>,
+    vertices: Vec<Vec2>,
+}
+
+fn ui_image_shapes(
+    mut contexts: EguiContexts,
+    mut tool: ResMut<ShapeTool>,
+    mut shapes: ResMut<ImageShapes>,
+) {
+    egui::Window::new("Image shapes")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("Pick a shape, then click on the main image plane");
+            ui.horizontal(|ui| {
+                for kind in ShapeKind::ALL {
+                    if ui
+                        .selectable_label(tool.kind == Some(kind), kind.label())
+                        .clicked()
+                    {
+                        *tool = ShapeTool {
+                            kind: (tool.kind != Some(kind)).then_some(kind),
+                            vertices: Vec::new(),
+                        };
+                    }
+                }
+            });
+
+            if let Some(kind) = tool.kind {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} vertices", tool.vertices.len()));
+                    let shape = kind.build(&tool.vertices);
+                    if ui
+                        .add_enabled(shape.is_some(), egui::Button::new("Finish"))
+                        .clicked()
+                    {
+                        shapes.shapes.extend(shape);
+                        tool.vertices.clear();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        *tool = ShapeTool::default();
+                    }
+                });
+            }
+            ui.separator();
+
+            let mut remove = None;
+            for (index, shape) in shapes.shapes.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let (outline, _) = shape.outline();
+                    ui.label(format!(
+                        "{} ({} points)",
+                        shape.kind().label(),
+                        outline.len()
+                    ));
+                    if ui.button("Remove").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                shapes.shapes.remove(index);
+            }
+            if !shapes.shapes.is_empty() && ui.button("Remove all").clicked() {
+                shapes.shapes.clear();
+            }
+        });
+}
+
+/// Whether clicks on the main image plane go to the shape being placed
+pub fn is_placing_shape(tool: Res<ShapeTool>) -> bool {
+    tool.kind.is_some()
+}
+
+fn place_shape_vertices(
+    mut tool: ResMut<ShapeTool>,
+    mut shapes: ResMut<ImageShapes>,
+    mut clicks: EventReader<MainPlaneClick>,
+    camera: CameraModel,
+) {
+    for MainPlaneClick { normalized } in clicks.read() {
+        let Some(kind) = tool.kind else {
+            continue;
+        };
+
+        tool.vertices
+            .push(camera.intrinsics.denormalize(*normalized));
+
+        if kind.is_complete(&tool.vertices) {
+            shapes.shapes.extend(kind.build(&tool.vertices));
+            tool.vertices.clear();
+        }
+    }
+}
+
+/// Marks the back-projection of an [`ImageShape`]
+#[derive(Debug, Component)]
+struct ShapeSurface;
+
+/// The surface swept by the rays through the shape, between the optical centre and the furthest
+/// plane. A line gives a plane, a polygon a pyramid and a conic a cone. Under affine projections
+/// these become a strip, a prism and a cylinder instead.
+fn back_projected_surfaces(
+    mut commands: MainPointsCommands,
+    mut cache: MeshMaterialCache,
+    shapes: Res<ImageShapes>,
+    planes: Res<ImagePlanes>,
+    camera: CameraModel,
+    existing: Query<Entity, With<ShapeSurface>>,
+) {
+    for entity in &existing {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !shapes.show_surfaces {
+        return;
+    }
+
+    let far = planes.far_depth();
+    let material = cache.material(palettes::tailwind::AMBER_300.with_alpha(0.15).to_u8_array());
+
+    for (index, shape) in shapes.shapes.iter().enumerate() {
+        let (mut outline, closed) = shape.outline();
+        if closed {
+            outline.extend(outline.first().copied());
+        }
+
+        let mut positions = Vec::new();
+        for pair in outline.windows(2) {
+            let [near_a, near_b] = [pair[0], pair[1]].map(|p| camera.back_project(p, 0.0));
+            let [far_a, far_b] = [pair[0], pair[1]].map(|p| camera.back_project(p, far));
+            positions.extend([near_a, far_a, far_b]);
+            // Under perspective the near edge collapses into the optical centre
+            if near_a.distance(near_b) > f32::EPSILON {
+                positions.extend([near_a, far_b, near_b]);
+            }
+        }
+        if positions.is_empty() {
+            continue;
+        }
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.compute_flat_normals();
+        let mesh = cache.add_mesh(mesh);
+
+        commands.child_builder(|b| {
+            b.spawn((
+                MaterialMeshBundle {
+                    mesh,
+                    material: material.clone(),
+                    ..default()
+                },
+                // Should not get in the way of the points inside it
+                Pickable::IGNORE,
+                ShapeSurface,
+                Name::new(format!("shape surface {index}")),
+            ));
+        });
+    }
+}
+
+/// Each shape on every image plane, and the vertices of the shape being placed
+fn gizmo_image_shapes(
+    mut gizmos: Gizmos,
+    shapes: Res<ImageShapes>,
+    tool: Res<ShapeTool>,
+    planes: Res<ImagePlanes>,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    camera: CameraModel,
+) {
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+    let color = palettes::tailwind::AMBER_400;
+
+    for shape in &shapes.shapes {
+        let (outline, closed) = shape.outline();
+
+        for depth in planes.depths() {
+            let to_plane = |p: &Vec2| rig.transform_point(camera.back_project(*p, depth));
+            gizmos.linestrip(
+                outline
+                    .iter()
+                    .chain(outline.first().filter(|_| closed))
+                    .map(to_plane),
+                color,
+            );
+        }
+    }
+
+    let depth = planes.main_depth();
+    let vertices: Vec<_> = tool
+        .vertices
+        .iter()
+        .map(|p| rig.transform_point(camera.back_project(*p, depth)))
+        .collect();
+    for vertex in &vertices {
+        gizmos.sphere(
+            *vertex,
+            Quat::default(),
+            point_settings.point_size / 2.,
+            color,
+        );
+    }
+    gizmos.linestrip(vertices, color.with_alpha(0.5));
+}
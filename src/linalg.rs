@@ -0,0 +1,215 @@
+//! Small dense linear algebra for the estimation problems (triangulation, homographies, pose),
+//! which need more than the fixed size matrices of glam.
+//!
+//! Matrices are row major `Vec`s of rows, in `f64` as the systems are often badly conditioned.
+
+/// Eigenvalues in ascending order, each with its unit eigenvector, of a symmetric matrix.
+/// Uses cyclic Jacobi rotations, which is plenty for the sizes used here (up to 12x12).
+pub fn symmetric_eigen(matrix: &[Vec<f64>]) -> Vec<(f64, Vec<f64>)> {
+    const MAX_SWEEPS: usize = 64;
+
+    let n = matrix.len();
+    let mut a = matrix.to_vec();
+    let mut v: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+
+    let scale: f64 = a.iter().flatten().map(|x| x * x).sum();
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off_diagonal <= scale * 1e-30 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+
+                // The rotation which zeroes a[p][q], see Numerical Recipes (11.1)
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = (t * t + 1.0).sqrt().recip();
+                let s = t * c;
+
+                for row in a.iter_mut().chain(v.iter_mut()) {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (upper, lower) = a.split_at_mut(q);
+                for (pk, qk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+                    (*pk, *qk) = (c * *pk - s * *qk, s * *pk + c * *qk);
+                }
+            }
+        }
+    }
+
+    let mut pairs: Vec<_> = (0..n)
+        .map(|j| (a[j][j], v.iter().map(|row| row[j]).collect::<Vec<_>>()))
+        .collect();
+    pairs.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    pairs
+}
+
+/// AᵀA
+pub fn normal_matrix(rows: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = rows.first().map_or(0, Vec::len);
+
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| rows.iter().map(|row| row[i] * row[j]).sum())
+                .collect()
+        })
+        .collect()
+}
+
+/// The unit x minimising |Ax|, i.e. the last right singular vector of A, with the smallest
+/// eigenvalue of AᵀA (the squared residual).
+/// `None` for a matrix without rows or with non-finite entries.
+pub fn null_vector(rows: &[Vec<f64>]) -> Option<(Vec<f64>, f64)> {
+    if rows.is_empty() || rows.iter().flatten().any(|x| !x.is_finite()) {
+        return None;
+    }
+
+    symmetric_eigen(&normal_matrix(rows))
+        .into_iter()
+        .next()
+        .map(|(value, vector)| (vector, value))
+}
+
+/// The x for which Ax = b, for a square A, using Gaussian elimination with partial pivoting.
+/// `None` for a (nearly) singular A.
+pub fn solve(a: &[Vec<f64>], b: &[f64]) -> Option<Vec<f64>> {
+    let n = b.len();
+    let mut m: Vec<Vec<f64>> = a
+        .iter()
+        .zip(b)
+        .map(|(row, b)| row.iter().copied().chain([*b]).collect())
+        .collect();
+    let scale = m
+        .iter()
+        .flat_map(|row| &row[..n])
+        .fold(0.0, |acc: f64, x| acc.max(x.abs()));
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| m[i][col].abs().total_cmp(&m[j][col].abs()))?;
+        if m[pivot][col].abs() <= 1e-12 * scale {
+            return None;
+        }
+        m.swap(col, pivot);
+
+        let (upper, lower) = m.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for row in lower {
+            let factor = row[col] / pivot_row[col];
+            for (x, p) in row.iter_mut().zip(pivot_row).skip(col) {
+                *x -= factor * p;
+            }
+        }
+    }
+
+    // Back substitution, from the last row up
+    let mut x = vec![0.0; n];
+    for (i, row) in m.iter().enumerate().rev() {
+        let known: f64 = row[i + 1..n]
+            .iter()
+            .zip(&x[i + 1..])
+            .map(|(a, x)| a * x)
+            .sum();
+        x[i] = (row[n] - known) / row[i];
+    }
+
+    Some(x)
+}
+
+/// The x minimising |Ax - b|, through the normal equations AᵀAx = Aᵀb
+pub fn least_squares(rows: &[Vec<f64>], rhs: &[f64]) -> Option<Vec<f64>> {
+    let n = rows.first().map_or(0, Vec::len);
+    let projected: Vec<f64> = (0..n)
+        .map(|i| rows.iter().zip(rhs).map(|(row, b)| row[i] * b).sum())
+        .collect();
+
+    solve(&normal_matrix(rows), &projected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric_eigen_diagonalizes() {
+        let matrix = vec![
+            vec![4.0, 1.0, -2.0],
+            vec![1.0, 2.0, 0.5],
+            vec![-2.0, 0.5, 3.0],
+        ];
+        let eigen = symmetric_eigen(&matrix);
+
+        assert!(eigen.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        for (value, vector) in &eigen {
+            let length: f64 = vector.iter().map(|x| x * x).sum();
+            assert!((length - 1.0).abs() < 1e-12);
+
+            for (row, x) in matrix.iter().zip(vector) {
+                let product: f64 = row.iter().zip(vector).map(|(a, v)| a * v).sum();
+                assert!((product - value * x).abs() < 1e-10);
+            }
+        }
+
+        // The trace is the sum of the eigenvalues
+        let sum: f64 = eigen.iter().map(|(value, _)| value).sum();
+        assert!((sum - 9.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn symmetric_eigen_of_known_matrix() {
+        let eigen = symmetric_eigen(&[vec![2.0, 1.0], vec![1.0, 2.0]]);
+
+        assert!((eigen[0].0 - 1.0).abs() < 1e-12);
+        assert!((eigen[1].0 - 3.0).abs() < 1e-12);
+        // (1, -1) / √2 up to sign
+        assert!((eigen[0].1[0] + eigen[0].1[1]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn null_vector_of_rank_deficient_rows() {
+        let (x, residual) = null_vector(&[vec![1.0, 2.0, 3.0], vec![2.0, 4.0, 7.0]]).unwrap();
+
+        assert!(residual.abs() < 1e-12);
+        // Proportional to (2, -1, 0)
+        assert!((x[0] + 2.0 * x[1]).abs() < 1e-12);
+        assert!(x[2].abs() < 1e-12);
+        assert!(null_vector(&[]).is_none());
+    }
+
+    #[test]
+    fn solve_known_system() {
+        // Needs a row swap, as the first pivot is zero
+        let x = solve(&[vec![0.0, 2.0], vec![3.0, 1.0]], &[4.0, 5.0]).unwrap();
+
+        assert!((x[0] - 1.0).abs() < 1e-12);
+        assert!((x[1] - 2.0).abs() < 1e-12);
+        assert!(solve(&[vec![1.0, 2.0], vec![2.0, 4.0]], &[1.0, 2.0]).is_none());
+    }
+
+    #[test]
+    fn least_squares_line_fit() {
+        // y = a + bx through (0, 1), (1, 2) and (2, 4), which are not on one line
+        let rows = [vec![1.0, 0.0], vec![1.0, 1.0], vec![1.0, 2.0]];
+        let x = least_squares(&rows, &[1.0, 2.0, 4.0]).unwrap();
+
+        assert!((x[0] - 5.0 / 6.0).abs() < 1e-12);
+        assert!((x[1] - 1.5).abs() < 1e-12);
+
+        // Collinear columns do not determine x
+        let rows = [vec![1.0, 2.0], vec![2.0, 4.0], vec![3.0, 6.0]];
+        assert!(least_squares(&rows, &[1.0, 2.0, 3.0]).is_none());
+    }
+}
@@ -1,53 +1,117 @@
 use bevy::{
     color::palettes,
     core_pipeline::Skybox,
-    ecs::system::SystemParam,
+    ecs::system::{EntityCommands, SystemParam},
     pbr::CascadeShadowConfigBuilder,
     prelude::*,
     render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
         texture::{ImageLoaderSettings, ImageSampler},
         view::RenderLayers,
     },
+    utils::HashSet,
 };
 use bevy_editor_cam::{prelude::EditorCam, DefaultEditorCamPlugins};
 use bevy_mod_picking::{
     debug::DebugPickingMode,
     events::{Move, Out, Pointer},
-    prelude::{ListenerInput, On},
+    prelude::{ListenerInput, On, Pickable, PickableBundle},
     DefaultPickingPlugins,
 };
-use egui_suppress::EguiSupressPlugin;
+use camera::{CameraModel, CameraPlugin};
+use distortion::DistortionPlugin;
+use egui_suppress::{EguiSupressPlugin, SuppressCameraWhilePressed};
+use epipolar::EpipolarPlugin;
 use gizmos::GizmosPlugin;
-use material_mesh_cache::{MaterialMeshCachePlugin, MeshMaterialCache};
+use homogeneous::HomogeneousPlugin;
+use homography::HomographyPlugin;
+use image_shapes::ImageShapesPlugin;
+use material_mesh_cache::{MaterialKey, MaterialMeshCachePlugin, MeshMaterialCache};
+use plane_homography::PlaneHomographyPlugin;
+use plane_image::{ImageFeatures, PlaneImageFeatures, PlaneImagePlugin};
+use point_distribution::PointDistribution;
+use point_editing::PointEditingPlugin;
+use point_labels::PointLabelsPlugin;
+use point_set::{LoadedPointSet, PointRecord, PointSetPlugin};
+use pose_estimation::PoseEstimationPlugin;
+use projection_matrix::ProjectionMatrixPlugin;
+use second_camera::SecondCameraPlugin;
+use seeded_rng::{SeededRng, SeededRngPlugin};
 use std::f32::consts::{FRAC_PI_4, PI};
 use transform_gizmo_bevy::{GizmoCamera, GizmoTarget, TransformGizmoPlugin};
+use triangulation::TriangulationPlugin;
 use ui_settings::UiSettingsPlugin;
 use viewport_camera::ViewportCameraPlugin;
+use world_points::WorldPointsPlugin;
 
-fn should_remake(point: Res<ImagePoints>, planes: Res<ImagePlanes>, size: Res<ImageSize>) -> bool {
-    point.is_changed() || planes.is_changed() || size.is_changed()
+fn should_remake(
+    point: Res<ImagePoints>,
+    planes: Res<ImagePlanes>,
+    size: Res<ImageSize>,
+    camera: CameraModel,
+    point_set: LoadedPointSet,
+    image_features: PlaneImageFeatures,
+) -> bool {
+    point.is_changed()
+        || planes.is_changed()
+        || size.is_changed()
+        || camera.is_changed()
+        || point_set.is_changed()
+        || image_features.is_changed()
 }
 
 // Potentially re-usable stuff
 pub mod egui_suppress;
 pub mod gizmos;
+pub mod linalg;
 pub mod material_mesh_cache;
+pub mod seeded_rng;
 pub mod viewport_camera;
 
 // Very this-project specific stuff
+pub mod camera;
+pub mod coords;
+pub mod distortion;
+pub mod epipolar;
+pub mod homogeneous;
+pub mod homography;
+pub mod image_shapes;
+pub mod plane_homography;
+pub mod plane_image;
+pub mod point_distribution;
+pub mod point_editing;
+pub mod point_labels;
+pub mod point_set;
+pub mod pose_estimation;
+pub mod projection_matrix;
+pub mod second_camera;
+pub mod sensor_noise;
+pub mod triangulation;
 pub mod ui_settings;
+pub mod world_points;
 
 const MISC_LAYER: usize = 1;
+/// Only seen by the [`SecondaryCamera`], like [`MISC_LAYER`] is only seen by the [`MainCamera`]
+const SECONDARY_LAYER: usize = 2;
 
 fn main() {
     App::new()
         .init_resource::<ImagePlanes>()
         .register_type::<ImagePlanes>()
+        .register_type::<PlaneSpacing>()
         .init_resource::<ImagePoints>()
         .register_type::<ImagePoints>()
+        .register_type::<PointDistribution>()
         .init_resource::<ImageSize>()
         .register_type::<ImageSize>()
+        .init_resource::<ImageResolution>()
+        .register_type::<ImageResolution>()
+        .init_resource::<FirstPlaneHover>()
         .register_type::<ImagePointIndex>()
+        .register_type::<SensorPosition>()
+        .register_type::<PointLabel>()
+        .register_type::<PointGroup>()
         .add_plugins((
             DefaultPlugins,
             DefaultPickingPlugins,
@@ -56,30 +120,65 @@ fn main() {
         ))
         .add_plugins((
             MaterialMeshCachePlugin,
+            SeededRngPlugin,
             ViewportCameraPlugin,
             GizmosPlugin,
             UiSettingsPlugin,
             EguiSupressPlugin,
         ))
+        .add_plugins((
+            PointSetPlugin,
+            PlaneImagePlugin,
+            CameraPlugin,
+            DistortionPlugin,
+            EpipolarPlugin,
+            HomogeneousPlugin,
+            HomographyPlugin,
+            ImageShapesPlugin,
+            PointEditingPlugin,
+            PointLabelsPlugin,
+            PlaneHomographyPlugin,
+        ))
+        .add_plugins((
+            PoseEstimationPlugin,
+            ProjectionMatrixPlugin,
+            SecondCameraPlugin,
+            TriangulationPlugin,
+            WorldPointsPlugin,
+        ))
         .insert_resource(DebugPickingMode::Normal)
         .add_systems(Startup, (setup_parent_spatial, setup).chain())
+        // Before anything in Update samples, so no stream is ever drawn with an outdated seed
+        .add_systems(PreUpdate, sync_seed)
         .add_systems(
             Update,
-            (clear, image_planes, generate_points, generate_sub_points)
+            (sync_image_planes, sync_image_points)
                 .chain()
                 .run_if(should_remake),
         )
         .add_systems(
             Update,
-            (animate_light_direction, propagate_follower_transforms),
+            sync_sub_points
+                .after(sync_image_points)
+                .run_if(should_remake.or_else(image_points_moved)),
+        )
+        .add_systems(
+            Update,
+            (
+                animate_light_direction,
+                propagate_follower_transforms,
+                face_image_planes.run_if(resource_changed::<ImagePlanes>),
+            ),
         )
         .add_event::<MoveOverFirstPlaneEvent>()
         .add_event::<MoveOutFirstPlaneEvent>()
         .run();
 }
 
-fn clear(mut commands: Commands, parent: Res<MainPointsParent>) {
-    commands.entity(**parent).despawn_descendants();
+fn sync_seed(points: Res<ImagePoints>, mut rng: ResMut<SeededRng>) {
+    if rng.seed() != points.seed {
+        rng.set_seed(points.seed);
+    }
 }
 
 #[derive(Debug, Resource, Deref, DerefMut, Reflect)]
@@ -92,6 +191,17 @@ impl Default for ImageSize {
     }
 }
 
+/// Pixel resolution of the sensor, see [`coords`]
+#[derive(Debug, Resource, Deref, DerefMut, Reflect)]
+#[reflect(Resource)]
+struct ImageResolution(UVec2);
+
+impl Default for ImageResolution {
+    fn default() -> Self {
+        Self(UVec2::new(1920, 1080))
+    }
+}
+
 #[derive(Debug, Component)]
 struct MainCamera;
 
@@ -135,7 +245,7 @@ fn setup(
         GizmoCamera,
         Skybox {
             image: asset_server.load_with_settings::<Image, ImageLoaderSettings>(
-                format!("skyboxes/circus_arena_4k_diffuse.ktx2"),
+                "skyboxes/circus_arena_4k_diffuse.ktx2",
                 |settings| {
                     settings.sampler = ImageSampler::linear();
                 },
@@ -162,7 +272,7 @@ fn setup(
         },
         Skybox {
             image: asset_server.load_with_settings::<Image, ImageLoaderSettings>(
-                format!("skyboxes/circus_arena_4k_diffuse.ktx2"),
+                "skyboxes/circus_arena_4k_diffuse.ktx2",
                 |settings| {
                     settings.sampler = ImageSampler::linear();
                 },
@@ -174,6 +284,7 @@ fn setup(
             specular_map: asset_server.load("environment_maps/pisa_specular_rgb9e5_zstd.ktx2"),
             intensity: 250.0,
         },
+        RenderLayers::default().with(SECONDARY_LAYER),
         viewport_camera::ViewportCamera {
             anchor: viewport_camera::Anchor::BottomRight,
             fraction: Vec2::new(0.3, 0.3),
@@ -183,7 +294,7 @@ fn setup(
             target: **main_points_entity,
             translation: true,
             scale: true,
-            rotation: true,
+            rotation: Some(Transform::default().looking_at(Vec3::Z, Vec3::Y).rotation),
         },
         SecondaryCamera,
     ));
@@ -194,7 +305,8 @@ struct CopyTransformOf {
     target: Entity,
     translation: bool,
     scale: bool,
-    rotation: bool,
+    /// If set, copy the rotation of the target followed by this rotation
+    rotation: Option<Quat>,
 }
 
 fn propagate_follower_transforms(
@@ -222,26 +334,54 @@ fn propagate_follower_transforms(
         if *scale {
             transform.scale = target.scale;
         }
-        if *rotation {
-            // transform.rotation = target.rotation;
+        if let Some(rotation) = rotation {
+            transform.rotation = target.rotation * *rotation;
         }
     }
 }
 
+/// The camera rig: The optical centre, with the image planes and their points as children.
+/// Placed by [`camera::CameraExtrinsics`].
 #[derive(Debug, Resource, Deref)]
 struct MainPointsParent {
     entity: Entity,
 }
 
+/// Point the secondary camera towards the image planes, which may be behind the optical centre
+fn face_image_planes(
+    planes: Res<ImagePlanes>,
+    mut cameras: Query<&mut CopyTransformOf, With<SecondaryCamera>>,
+) {
+    let forward = Vec3::Z * planes.main_depth().signum();
+
+    for mut copy in &mut cameras {
+        copy.rotation = Some(Transform::default().looking_at(forward, Vec3::Y).rotation);
+    }
+}
+
+/// Parent of geometry which lives in the world, independent of the camera
+#[derive(Debug, Resource, Deref)]
+struct WorldPointsParent {
+    entity: Entity,
+}
+
 fn setup_parent_spatial(mut commands: Commands) {
     let id = commands
         .spawn((
             SpatialBundle::INHERITED_IDENTITY,
-            Name::new(format!("main points parent")),
-            GizmoTarget::default(),
+            Name::new("main points parent"),
         ))
         .id();
     commands.insert_resource(MainPointsParent { entity: id });
+
+    let id = commands
+        .spawn((
+            SpatialBundle::INHERITED_IDENTITY,
+            Name::new("world points parent"),
+            GizmoTarget::default(),
+        ))
+        .id();
+    commands.insert_resource(WorldPointsParent { entity: id });
 }
 
 fn animate_light_direction(
@@ -262,17 +402,101 @@ fn animate_light_direction(
 #[reflect(Resource)]
 struct ImagePlanes {
     num_planes: usize,
+
+    /// Depth of the main image plane
+    first_depth: f32,
+    spacing: PlaneSpacing,
+
+    /// Mirror the planes to behind the optical centre, like the sensor of a real pinhole camera.
+    /// The image on them is then upside down.
+    physical: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect)]
+enum PlaneSpacing {
+    /// Evenly spaced in depth
+    Linear { step: f32 },
+
+    /// Each plane is `ratio` times as deep as the previous one
+    Geometric { ratio: f32 },
+
+    /// Evenly spaced in inverse depth (i.e. disparity), ending at `last_depth`
+    InverseDepth { last_depth: f32 },
+
+    /// Exactly these depths, ignoring `num_planes` and `first_depth`
+    Explicit(Vec<f32>),
+}
+
+impl ImagePlanes {
+    /// Depth of each plane, starting with the main image plane.
+    /// Negative depths are behind the optical centre.
+    fn depths(&self) -> Vec<f32> {
+        let sign = if self.physical { -1.0 } else { 1.0 };
+
+        self.unsigned_depths()
+            .into_iter()
+            .map(|depth| depth * sign)
+            .collect()
+    }
+
+    fn unsigned_depths(&self) -> Vec<f32> {
+        let n = self.num_planes;
+        let first = self.first_depth;
+
+        match &self.spacing {
+            PlaneSpacing::Linear { step } => (0..n).map(|i| first + i as f32 * step).collect(),
+            PlaneSpacing::Geometric { ratio } => {
+                (0..n).map(|i| first * ratio.powi(i as i32)).collect()
+            }
+            PlaneSpacing::InverseDepth { last_depth } => {
+                let (first_disparity, last_disparity) = (first.recip(), last_depth.recip());
+                let steps = n.saturating_sub(1).max(1) as f32;
+
+                (0..n)
+                    .map(|i| {
+                        let t = i as f32 / steps;
+                        (first_disparity + t * (last_disparity - first_disparity)).recip()
+                    })
+                    .collect()
+            }
+            PlaneSpacing::Explicit(depths) => depths.clone(),
+        }
+        .into_iter()
+        .filter(|depth| depth.is_finite())
+        .collect()
+    }
+
+    /// Depth of the main image plane, which the image points live on
+    fn main_depth(&self) -> f32 {
+        self.depths().first().copied().unwrap_or(self.first_depth)
+    }
+
+    /// The plane furthest away from the optical centre, in either direction
+    fn far_depth(&self) -> f32 {
+        self.depths()
+            .into_iter()
+            .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+            .unwrap_or(self.first_depth)
+    }
 }
 
 #[derive(Debug, Component)]
-struct ImagePlane;
+struct ImagePlane {
+    /// Into [`ImagePlanes::depths`]
+    index: usize,
+}
 
 #[derive(Debug, Component)]
 struct MainImagePlane;
 
 impl Default for ImagePlanes {
     fn default() -> Self {
-        Self { num_planes: 7 }
+        Self {
+            num_planes: 7,
+            first_depth: 1.0,
+            spacing: PlaneSpacing::Linear { step: 1.0 },
+            physical: false,
+        }
     }
 }
 
@@ -288,6 +512,44 @@ impl<'w, 's> MainPointsCommands<'w, 's> {
             .entity(**self.parent)
             .with_children(child_builder_fn);
     }
+
+    fn entity(&mut self, entity: Entity) -> EntityCommands<'_> {
+        self.commands.entity(entity)
+    }
+}
+
+/// Where the camera rig and the world parent currently are
+#[derive(SystemParam)]
+struct ParentTransforms<'w, 's> {
+    rig: Res<'w, MainPointsParent>,
+    world: Res<'w, WorldPointsParent>,
+    transforms: Query<'w, 's, &'static GlobalTransform>,
+}
+
+impl ParentTransforms<'_, '_> {
+    /// Camera space to world space
+    fn rig(&self) -> Option<&GlobalTransform> {
+        self.transforms.get(**self.rig).ok()
+    }
+
+    fn world(&self) -> Option<&GlobalTransform> {
+        self.transforms.get(**self.world).ok()
+    }
+
+    fn world_entity(&self) -> Entity {
+        **self.world
+    }
+}
+
+/// Where the pointer is over the main image plane, if it is
+#[derive(Debug, Default, Resource, Deref)]
+struct FirstPlaneHover(Option<HoverPosition>);
+
+#[derive(Debug, Clone, Copy)]
+struct HoverPosition {
+    world: Vec3,
+    /// Camera space XY on the main image plane, i.e. normalized image coordinates
+    normalized: Vec2,
 }
 
 #[derive(Event, Debug, Deref)]
@@ -316,36 +578,84 @@ impl From<ListenerInput<Pointer<Out>>> for MoveOutFirstPlaneEvent {
     }
 }
 
-fn image_planes(
+/// The sensor corners in counter-clockwise order, starting bottom left
+fn sensor_corners(size: &ImageSize) -> [Vec2; 4] {
+    let half = **size / 2.;
+    [
+        Vec2::new(-half.x, -half.y),
+        Vec2::new(half.x, -half.y),
+        Vec2::new(half.x, half.y),
+        Vec2::new(-half.x, half.y),
+    ]
+}
+
+/// A quad in XY spanned by the given corners (as given by [`sensor_corners`]).
+/// UVs are laid out such that (0, 0) is the top left corner.
+fn image_plane_mesh(corners: [Vec2; 4]) -> Mesh {
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        corners.map(|c| c.extend(0.0)).to_vec(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![Vec3::NEG_Z; 4])
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        vec![[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]],
+    )
+    .with_inserted_indices(Indices::U32(vec![0, 1, 2, 0, 2, 3]))
+}
+
+/// The material of image planes which do not show the [`plane_image::PlaneImage`]
+fn plain_plane_material(cache: &mut MeshMaterialCache) -> Handle<StandardMaterial> {
+    cache.material(palettes::tailwind::GREEN_300.with_alpha(0.05).to_u8_array())
+}
+
+fn sync_image_planes(
     mut commands: MainPointsCommands,
     mut cache: MeshMaterialCache,
     size: Res<ImageSize>,
     planes: Res<ImagePlanes>,
+    camera: CameraModel,
+    mut existing: Query<(Entity, &ImagePlane, &mut Transform, &mut Handle<Mesh>)>,
 ) {
-    for i in 1..=planes.num_planes {
-        let i_f32 = i as f32;
+    // The sensor in normalized image coordinates, which the projection then places at each depth
+    let mesh = cache.add_mesh(image_plane_mesh(
+        sensor_corners(&size).map(|corner| camera.intrinsics.normalize(corner)),
+    ));
+    let depths = planes.depths();
+
+    for (entity, ImagePlane { index }, mut transform, mut plane_mesh) in &mut existing {
+        match depths.get(*index) {
+            Some(&depth) => {
+                *transform = camera.projection.plane_transform(depth);
+                *plane_mesh = mesh.clone();
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
 
+    for (i, depth) in depths.into_iter().enumerate().skip(existing.iter().count()) {
         commands.child_builder(|b| {
             let mut cmds = b.spawn((
                 MaterialMeshBundle {
-                    mesh: cache.mesh::<Plane3d>(),
-                    material: cache
-                        .material(palettes::tailwind::GREEN_300.with_alpha(0.05).to_u8_array()),
-                    transform: Transform::from_translation(Vec3::Z * i_f32)
-                        // Plane has normal +Y, so size is defined in terms of XZ.
-                        .with_scale(Vec3::new(size.x * i_f32, 0.01, size.y * i_f32))
-                        // We then also have to rotate 90 deg in X to have a plane in XY.
-                        .with_rotation(Quat::from_axis_angle(
-                            Vec3::X,
-                            -std::f32::consts::FRAC_PI_2,
-                        )),
+                    mesh: mesh.clone(),
+                    material: plain_plane_material(&mut cache),
+                    transform: camera.projection.plane_transform(depth),
                     ..default()
                 },
-                ImagePlane,
-                Name::new(format!("plane-{i}")),
+                // Let things behind the planes (e.g. world points) be picked too
+                Pickable {
+                    should_block_lower: false,
+                    is_hoverable: true,
+                },
+                ImagePlane { index: i },
+                Name::new(format!("plane-{}", i + 1)),
             ));
 
-            if i == 1 {
+            if i == 0 {
                 cmds.insert((
                     On::<Pointer<Move>>::send_event::<MoveOverFirstPlaneEvent>(),
                     On::<Pointer<Out>>::send_event::<MoveOutFirstPlaneEvent>(),
@@ -361,6 +671,10 @@ fn image_planes(
 struct ImagePoints {
     num_points: usize,
     point_size: f32,
+    distribution: PointDistribution,
+
+    /// Seed for everything random, see [`SeededRng`]
+    seed: u64,
 }
 
 impl Default for ImagePoints {
@@ -368,6 +682,8 @@ impl Default for ImagePoints {
         Self {
             num_points: 10,
             point_size: 0.05,
+            distribution: PointDistribution::default(),
+            seed: 42,
         }
     }
 }
@@ -380,65 +696,354 @@ struct ImagePointIndex {
     index: usize,
 }
 
-/// For points in planes with Z > 1.0
+/// Where an [`ImagePoint`] is on the sensor, see [`camera::CameraIntrinsics`] for the units used
+#[derive(Debug, Clone, Copy, Component, Reflect, Deref)]
+struct SensorPosition(Vec2);
+
+/// Optional name of an [`ImagePoint`], e.g. from a [`point_set::PointSet`]
+#[derive(Debug, Clone, Component, Reflect, Deref)]
+struct PointLabel(String);
+
+/// Optional group of an [`ImagePoint`], see [`point_labels::PointGroups`]
+#[derive(Debug, Clone, Component, Reflect, Deref)]
+struct PointGroup(String);
+
+/// Marks a [`PointLabel`] which came from the [`PointRecord`] of the point rather than being typed
+/// in, so resampling may replace or remove it
+#[derive(Debug, Component)]
+struct RecordLabel;
+
+/// Like [`RecordLabel`], for the [`PointGroup`]
+#[derive(Debug, Component)]
+struct RecordGroup;
+
+/// Give a point the label and group of the record. Annotations the record has none of are only
+/// removed if they came from an earlier record, so the ones typed in by hand survive resampling.
+fn insert_annotations(
+    cmds: &mut EntityCommands,
+    record: &PointRecord,
+    (label_from_record, group_from_record): (bool, bool),
+) {
+    match &record.label {
+        Some(label) => {
+            cmds.insert((PointLabel(label.clone()), RecordLabel));
+        }
+        None if label_from_record => {
+            cmds.remove::<(PointLabel, RecordLabel)>();
+        }
+        None => {}
+    }
+    match &record.group {
+        Some(group) => {
+            cmds.insert((PointGroup(group.clone()), RecordGroup));
+        }
+        None if group_from_record => {
+            cmds.remove::<(PointGroup, RecordGroup)>();
+        }
+        None => {}
+    }
+}
+
+/// The material an [`ImagePoint`] and its sub-points are drawn with
+#[derive(Debug, Clone, Copy, Component, Deref)]
+struct PointMaterial(MaterialKey);
+
+/// For points in planes other than the main image plane
 #[derive(Debug, Component)]
-struct SubImagePoint;
+struct SubImagePoint {
+    /// Into [`ImagePlanes::depths`], never 0
+    plane_index: usize,
+}
+
+/// Stream of [`SeededRng`] used for [`ImagePoint`] positions
+const IMAGE_POINTS_STREAM: u64 = 1;
 
-fn generate_points(
+/// What the positions of the [`ImagePoint`]s are sampled from
+#[derive(Debug, Clone, PartialEq)]
+struct PointSampling {
+    num_points: usize,
+    distribution: PointDistribution,
+    seed: u64,
+    size: Vec2,
+    features: ImageFeatures,
+}
+
+/// Where the [`ImagePoint`]s come from: A loaded point set if there is one, then features of the
+/// image on the planes if wanted, generated otherwise
+#[derive(SystemParam)]
+struct ImagePointSource<'w> {
+    settings: Res<'w, ImagePoints>,
+    size: Res<'w, ImageSize>,
+    rng: Res<'w, SeededRng>,
+    point_set: LoadedPointSet<'w>,
+    image_features: PlaneImageFeatures<'w>,
+}
+
+impl ImagePointSource<'_> {
+    fn sampling(&self) -> PointSampling {
+        PointSampling {
+            num_points: self.settings.num_points,
+            distribution: self.settings.distribution.clone(),
+            seed: self.rng.seed(),
+            size: **self.size,
+            features: self.image_features.features().clone(),
+        }
+    }
+
+    /// Whether a file the points come from was (re)loaded, or how it is read changed
+    fn is_reloaded(&self) -> bool {
+        self.point_set.is_changed() || self.image_features.is_reloaded()
+    }
+
+    /// Points in metric sensor coordinates
+    fn points(&self) -> Vec<PointRecord> {
+        if let Some(points) = self.point_set.points(**self.size) {
+            return points;
+        }
+        if let Some(points) = self
+            .image_features
+            .points(self.settings.num_points, **self.size)
+        {
+            return points;
+        }
+
+        let mut rng = self.rng.stream(IMAGE_POINTS_STREAM);
+        self.settings
+            .distribution
+            .sample(self.settings.num_points, **self.size, &mut rng)
+            .into_iter()
+            .map(|position| PointRecord {
+                position,
+                label: None,
+                group: None,
+                color: None,
+            })
+            .collect()
+    }
+}
+
+fn point_style(index: usize, record: &PointRecord) -> (PointMaterial, Name) {
+    let material = PointMaterial(match record.color {
+        Some(color) => LinearRgba::from(color).to_u8_array().into(),
+        None => index.into(),
+    });
+    let name = match &record.label {
+        Some(label) => format!("point-{index} ({label})"),
+        None => format!("point-{index}"),
+    };
+
+    (material, Name::new(name))
+}
+
+/// An [`ImagePoint`] which came from [`ImagePointSource`], as opposed to e.g. being placed by hand
+#[derive(Debug, Clone, Copy, Component)]
+struct SampledPoint {
+    /// Into [`ImagePointSource::points`]
+    sample: usize,
+}
+
+/// An [`ImagePointIndex`] which is not used yet
+fn next_point_index<'a>(indices: impl IntoIterator<Item = &'a ImagePointIndex>) -> usize {
+    indices
+        .into_iter()
+        .map(|index| index.index + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Spawn an [`ImagePoint`] on the camera rig
+fn spawn_image_point(
+    commands: &mut MainPointsCommands,
+    cache: &mut MeshMaterialCache,
+    index: usize,
+    record: PointRecord,
+    transform: Transform,
+) -> Entity {
+    let (material, name) = point_style(index, &record);
+    let mut entity = Entity::PLACEHOLDER;
+
+    commands.child_builder(|b| {
+        let mut cmds = b.spawn((
+            MaterialMeshBundle {
+                mesh: cache.mesh::<Sphere>(),
+                material: cache.material(*material),
+                transform,
+                ..default()
+            },
+            PickableBundle::default(),
+            SuppressCameraWhilePressed,
+            ImagePoint,
+            ImagePointIndex { index },
+            SensorPosition(record.position),
+            material,
+            name,
+        ));
+
+        insert_annotations(&mut cmds, &record, (false, false));
+        entity = cmds.id();
+    });
+
+    entity
+}
+
+/// Update the [`ImagePoint`]s in place, only spawning and despawning the difference in count.
+/// The sequences are deterministic, so increasing the number of points keeps the existing ones
+/// where they were for the distributions where that makes sense.
+#[allow(clippy::type_complexity)]
+fn sync_image_points(
     mut commands: MainPointsCommands,
     mut cache: MeshMaterialCache,
-    size: Res<ImageSize>,
-    points: Res<ImagePoints>,
+    planes: Res<ImagePlanes>,
+    camera: CameraModel,
+    source: ImagePointSource,
+    mut last_sampling: Local<Option<PointSampling>>,
+    mut existing: Query<
+        (
+            Entity,
+            &ImagePointIndex,
+            Option<&SampledPoint>,
+            (Has<RecordLabel>, Has<RecordGroup>),
+            &mut SensorPosition,
+            &mut Transform,
+        ),
+        With<ImagePoint>,
+    >,
 ) {
-    let rect = Rectangle::new(size.x, size.y);
+    // Positions only change when what they are sampled from does, not e.g. with the point size
+    let sampling = source.sampling();
+    let resample = source.is_reloaded() || last_sampling.as_ref() != Some(&sampling);
+    *last_sampling = Some(sampling);
 
-    for index in 0..points.num_points {
-        let pos = rect.sample_interior(&mut rand::thread_rng());
+    // Finding features in the image is expensive, so only sample when something it depends on
+    // changed, not for every change of the camera
+    let records = if resample {
+        source.points()
+    } else {
+        Vec::new()
+    };
+    let depth = planes.main_depth();
+    let scale = Vec3::splat(source.settings.point_size);
+    let mut present = HashSet::new();
 
-        commands.child_builder(|b| {
-            b.spawn((
-                MaterialMeshBundle {
-                    mesh: cache.mesh::<Sphere>(),
-                    material: cache.material(index),
-                    transform: Transform::from_translation(pos.extend(1.0))
-                        .with_scale(Vec3::splat(points.point_size)),
-                    ..default()
-                },
-                ImagePoint,
-                ImagePointIndex { index },
-                Name::new(format!("point-{index}")),
-            ));
-        });
+    for (
+        entity,
+        ImagePointIndex { index },
+        sampled,
+        from_record,
+        mut sensor_position,
+        mut transform,
+    ) in &mut existing
+    {
+        if let (true, Some(SampledPoint { sample })) = (resample, sampled) {
+            let Some(record) = records.get(*sample) else {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            };
+            present.insert(*sample);
+            sensor_position.0 = record.position;
+
+            let (material, name) = point_style(*index, record);
+            let handle = cache.material(*material);
+            let mut cmds = commands.entity(entity);
+            cmds.insert((handle, material, name));
+            insert_annotations(&mut cmds, record, from_record);
+        }
+
+        *transform = Transform::from_translation(camera.back_project(**sensor_position, depth))
+            .with_scale(scale);
+    }
+
+    // Samples which were deleted by hand only come back with a new sampling
+    if !resample {
+        return;
+    }
+    let mut index = next_point_index(existing.iter().map(|(_, index, ..)| index));
+    for (sample, record) in records.into_iter().enumerate() {
+        if present.contains(&sample) {
+            continue;
+        }
+
+        let transform = Transform::from_translation(camera.back_project(record.position, depth))
+            .with_scale(scale);
+        let entity = spawn_image_point(&mut commands, &mut cache, index, record, transform);
+        commands.entity(entity).insert(SampledPoint { sample });
+        index += 1;
     }
 }
 
-fn generate_sub_points(
+/// Also true for added points
+fn image_points_moved(points: Query<(), (With<ImagePoint>, Changed<SensorPosition>)>) -> bool {
+    !points.is_empty()
+}
+
+/// Keep one sub-point per [`ImagePoint`] on every plane but the main one
+fn sync_sub_points(
     mut commands: MainPointsCommands,
     mut cache: MeshMaterialCache,
     planes: Res<ImagePlanes>,
-    points: Query<(&ImagePointIndex, &Transform), With<ImagePoint>>,
+    camera: CameraModel,
+    points: Query<
+        (
+            &ImagePointIndex,
+            &SensorPosition,
+            &PointMaterial,
+            &Transform,
+        ),
+        With<ImagePoint>,
+    >,
+    mut sub_points: Query<
+        (Entity, &ImagePointIndex, &SubImagePoint, &mut Transform),
+        Without<ImagePoint>,
+    >,
 ) {
-    if planes.num_planes < 2 {
-        return;
+    let depths = planes.depths();
+    let mut existing = HashSet::new();
+
+    for (entity, ImagePointIndex { index }, SubImagePoint { plane_index }, mut transform) in
+        &mut sub_points
+    {
+        let point = points
+            .iter()
+            .find(|(point_index, ..)| point_index.index == *index);
+        let (Some((_, sensor_position, point_material, point_transform)), Some(&depth)) =
+            (point, depths.get(*plane_index))
+        else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        *transform = Transform::from_translation(camera.back_project(**sensor_position, depth))
+            .with_scale(point_transform.scale);
+        // The point may have been recolored, e.g. by a reloaded point set
+        let handle = cache.material(**point_material);
+        commands.entity(entity).insert((handle, *point_material));
+        existing.insert((*index, *plane_index));
     }
-    for (image_point_index, transform) in &points {
-        for plane_index in 2..=planes.num_planes {
-            let z = plane_index as f32;
-            let translation = transform.translation * z;
+
+    for (image_point_index, sensor_position, material, transform) in &points {
+        for (plane_index, &depth) in depths.iter().enumerate().skip(1) {
+            if existing.contains(&(image_point_index.index, plane_index)) {
+                continue;
+            }
+            let translation = camera.back_project(**sensor_position, depth);
 
             commands.child_builder(|b| {
                 b.spawn((
                     MaterialMeshBundle {
                         mesh: cache.mesh::<Sphere>(),
-                        material: cache.material(image_point_index.index),
+                        material: cache.material(**material),
                         transform: Transform::from_translation(translation)
                             .with_scale(transform.scale),
                         ..default()
                     },
-                    SubImagePoint,
+                    PickableBundle::default(),
+                    SuppressCameraWhilePressed,
+                    SubImagePoint { plane_index },
                     *image_point_index,
+                    *material,
                     Name::new(format!(
-                        "sub-point {plane_index}-{}",
+                        "sub-point {}-{}",
+                        plane_index + 1,
                         image_point_index.index
                     )),
                 ));
@@ -1,6 +1,9 @@
 use std::any::TypeId;
 
 use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use rand::Rng;
+
+use crate::seeded_rng::SeededRng;
 
 pub struct MaterialMeshCachePlugin;
 
@@ -11,7 +14,38 @@ impl Plugin for MaterialMeshCachePlugin {
             .register_type::<ColorCache>()
             .init_resource::<TypeIdMeshCache>()
             .init_resource::<MaterialsCache>()
-            .init_resource::<ColorCache>();
+            .init_resource::<ColorCache>()
+            .add_systems(
+                Update,
+                recolor_seeded_materials.run_if(resource_changed::<SeededRng>),
+            );
+    }
+}
+
+/// Stream of [`SeededRng`] used for [`MaterialKey::Usize`] colors
+const COLOR_STREAM: u64 = 0xC010;
+
+fn seeded_color(rng: &SeededRng, index: usize) -> Color {
+    Color::srgb_from_array(rng.stream(COLOR_STREAM.wrapping_add(index as u64)).gen())
+}
+
+/// Keep handles to seeded materials valid, but give them the colors of the new seed
+fn recolor_seeded_materials(
+    rng: Res<SeededRng>,
+    mut material_assets: ResMut<Assets<StandardMaterial>>,
+    material_cache: Res<MaterialsCache>,
+    mut color_cache: ResMut<ColorCache>,
+) {
+    for (key, handle) in material_cache.iter() {
+        let MaterialKey::Usize(index) = key else {
+            continue;
+        };
+        let color = seeded_color(&rng, *index);
+
+        color_cache.insert(*key, color);
+        if let Some(material) = material_assets.get_mut(handle) {
+            material.base_color = color;
+        }
     }
 }
 
@@ -60,9 +94,11 @@ pub struct MeshMaterialCache<'w> {
     material_cache: ResMut<'w, MaterialsCache>,
 
     color_cache: ResMut<'w, ColorCache>,
+
+    rng: Res<'w, SeededRng>,
 }
 
-impl<'w> MeshMaterialCache<'_> {
+impl MeshMaterialCache<'_> {
     /// Weak handle to a default mesh of given type
     pub fn mesh<M: Meshable + Default + 'static>(&mut self) -> Handle<Mesh> {
         self.mesh_cache
@@ -71,6 +107,11 @@ impl<'w> MeshMaterialCache<'_> {
             .clone_weak()
     }
 
+    /// Strong handle to a one-off mesh which is not cached
+    pub fn add_mesh(&mut self, mesh: impl Into<Mesh>) -> Handle<Mesh> {
+        self.mesh_assets.add(mesh)
+    }
+
     fn material_color(&mut self, key: MaterialKey) -> (Handle<StandardMaterial>, Color) {
         if let Some(mat) = self.material_cache.get(&key) {
             let col = self.color_cache.get(&key).unwrap();
@@ -78,7 +119,7 @@ impl<'w> MeshMaterialCache<'_> {
             (mat.clone_weak(), *col)
         } else {
             let color = match key {
-                MaterialKey::Usize(_) => Color::srgb_from_array(rand::random()),
+                MaterialKey::Usize(index) => seeded_color(&self.rng, index),
                 MaterialKey::LinearRgba(color) => {
                     Color::LinearRgba(LinearRgba::from_u8_array(color))
                 }
@@ -0,0 +1,380 @@
+use bevy::{color::palettes, prelude::*};
+use bevy_inspector_egui::{
+    bevy_egui::EguiContexts, egui, inspector_options::ReflectInspectorOptions, InspectorOptions,
+};
+
+use crate::{
+    camera::{CameraIntrinsics, CameraProjection, ProjectionModel},
+    coords,
+    homography::apply_homography,
+    material_mesh_cache::MeshMaterialCache,
+    projection_matrix::matrix_label,
+    second_camera::{SecondCamera, SecondRig},
+    world_points::WorldPoint,
+    ImagePlanes, ImagePoints, ImageResolution, ImageSize, ParentTransforms,
+};
+
+/// A plane in the world, and the homography it induces between the main and the second camera
+pub struct PlaneHomographyPlugin;
+
+impl Plugin for PlaneHomographyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldPlane>()
+            .register_type::<WorldPlane>()
+            .init_resource::<InducedHomography>()
+            .add_systems(
+                Update,
+                (
+                    induce_homography,
+                    ui_plane_homography,
+                    gizmo_plane_homography,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The plane n·X = offset in world space, which moves along with the world points
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct WorldPlane {
+    enabled: bool,
+
+    /// Need not be of unit length
+    normal: Vec3,
+    /// Distance from the world origin along the normal
+    offset: f32,
+
+    /// Side length of the part of the plane which is drawn
+    #[inspector(min = 0.1)]
+    size: f32,
+    /// Points on the plane in each direction, to show the homography with
+    #[inspector(min = 1)]
+    grid_points: usize,
+
+    /// How far each world point is from where the homography puts it
+    show_parallax: bool,
+}
+
+impl Default for WorldPlane {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            normal: Vec3::new(0.3, 0.0, 1.0),
+            offset: 4.0,
+            size: 2.0,
+            grid_points: 3,
+            show_parallax: true,
+        }
+    }
+}
+
+impl WorldPlane {
+    /// The point of the plane closest to the world origin, with the plane rotated such that its
+    /// local Z is the normal
+    fn transform(&self) -> Option<Transform> {
+        let normal = self.normal.try_normalize()?;
+
+        Some(
+            Transform::from_translation(normal * self.offset)
+                .with_rotation(Quat::from_rotation_arc(Vec3::Z, normal)),
+        )
+    }
+
+    /// Evenly spread over the drawn part of the plane, in the coordinates of the plane
+    fn grid(&self) -> Vec<Vec2> {
+        let n = self.grid_points.max(1);
+        let coordinate = |i: usize| {
+            if n == 1 {
+                0.0
+            } else {
+                (i as f32 / (n - 1) as f32 - 0.5) * self.size
+            }
+        };
+
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| Vec2::new(coordinate(i), coordinate(j))))
+            .collect()
+    }
+}
+
+/// H = K'(R - t nᵀ/d)K⁻¹, which maps sensor positions in the main camera onto the second camera
+/// for points on the plane nᵀX + d = 0 in main camera space, see Hartley & Zisserman (13.1).
+/// Both cameras share K here. `None` for planes through the main optical centre.
+pub fn plane_induced_homography(
+    k: Mat3,
+    second: &SecondCamera,
+    normal: Vec3,
+    d: f32,
+) -> Option<Mat3> {
+    if d.abs() < f32::EPSILON {
+        return None;
+    }
+    let t = second.translation();
+    // t nᵀ, column j is t scaled by the j-th entry of n
+    let outer = Mat3::from_cols(t * normal.x, t * normal.y, t * normal.z);
+
+    Some(k * (second.rotation_matrix() - outer / d) * k.inverse())
+}
+
+/// The plane and its homography, for as long as both cameras are there to induce one
+#[derive(Debug, Default, Resource)]
+struct InducedHomography(Option<Induced>);
+
+#[derive(Debug, Clone, Copy)]
+struct Induced {
+    /// Unit normal of the plane nᵀX + d = 0, in main camera space
+    normal: Vec3,
+    d: f32,
+    homography: Mat3,
+}
+
+fn induce_homography(
+    mut induced: ResMut<InducedHomography>,
+    plane: Res<WorldPlane>,
+    intrinsics: Res<CameraIntrinsics>,
+    projection: Res<CameraProjection>,
+    parents: ParentTransforms,
+    second: SecondRig,
+) {
+    induced.0 = None;
+
+    if !plane.enabled || projection.model != ProjectionModel::Perspective {
+        return;
+    }
+    let (Some(world), Some(rig), Some(_), Some(plane_transform)) = (
+        parents.world(),
+        parents.rig(),
+        second.transform(),
+        plane.transform(),
+    ) else {
+        return;
+    };
+
+    let world_to_camera = rig.affine().inverse() * world.affine();
+    let point = world_to_camera.transform_point3(plane_transform.translation);
+    let Some(normal) = world_to_camera
+        .transform_vector3(plane_transform.rotation * Vec3::Z)
+        .try_normalize()
+    else {
+        return;
+    };
+    let d = -normal.dot(point);
+
+    induced.0 = plane_induced_homography(intrinsics.matrix(), &second.settings, normal, d).map(
+        |homography| Induced {
+            normal,
+            d,
+            homography,
+        },
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ui_plane_homography(
+    mut contexts: EguiContexts,
+    plane: Res<WorldPlane>,
+    induced: Res<InducedHomography>,
+    intrinsics: Res<CameraIntrinsics>,
+    size: Res<ImageSize>,
+    resolution: Res<ImageResolution>,
+    parents: ParentTransforms,
+    second: SecondRig,
+    points: Query<(&GlobalTransform, &WorldPoint)>,
+) {
+    if !plane.enabled {
+        return;
+    }
+
+    egui::Window::new("Plane-induced homography")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let (
+                Some(Induced {
+                    normal,
+                    d,
+                    homography,
+                }),
+                Some(rig),
+            ) = (induced.0, parents.rig())
+            else {
+                ui.label(
+                    "Needs both cameras under perspective, and a plane which does not go through \
+                     the main optical centre",
+                );
+                return;
+            };
+
+            ui.label("H = K(R - t nᵀ/d)K⁻¹");
+            ui.label(format!(
+                "In main camera space n: ({:.3}, {:.3}, {:.3}), d: {:.3}",
+                normal.x, normal.y, normal.z, d
+            ));
+            matrix_label(ui, homography);
+            ui.separator();
+
+            ui.label("Parallax of the world points relative to the plane, in pixels");
+            let to_pixel = |metric: Vec2| coords::metric_to_pixel(metric, **size, **resolution);
+            egui::Grid::new("parallax").show(ui, |ui| {
+                for (transform, WorldPoint { index }) in &points {
+                    let parallax = second
+                        .observe(&intrinsics, rig, transform.translation())
+                        .and_then(|(main, second)| {
+                            let mapped = apply_homography(homography, main)?;
+                            Some(to_pixel(second).distance(to_pixel(mapped)))
+                        });
+
+                    ui.label(format!("world-point-{index}"));
+                    ui.monospace(match parallax {
+                        Some(parallax) => format!("{parallax:.1}"),
+                        None => "-".to_string(),
+                    });
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+/// The plane, points on it as both cameras see them, and the parallax of the world points
+#[allow(clippy::too_many_arguments)]
+fn gizmo_plane_homography(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    plane: Res<WorldPlane>,
+    induced: Res<InducedHomography>,
+    intrinsics: Res<CameraIntrinsics>,
+    planes: Res<ImagePlanes>,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    second: SecondRig,
+    points: Query<(&GlobalTransform, &WorldPoint)>,
+) {
+    if !plane.enabled {
+        return;
+    }
+    let (Some(world), Some(plane_transform)) = (parents.world(), plane.transform()) else {
+        return;
+    };
+    let plane_to_world = world.mul_transform(plane_transform);
+    let color = palettes::tailwind::VIOLET_400;
+
+    let (_, rotation, translation) = plane_to_world.to_scale_rotation_translation();
+    gizmos.rect(
+        translation,
+        rotation,
+        Vec2::splat(plane.size),
+        color.with_alpha(0.5),
+    );
+
+    let (Some(Induced { homography, .. }), Some(rig), Some(second_rig)) =
+        (induced.0, parents.rig(), second.transform())
+    else {
+        return;
+    };
+    let depth = planes.main_depth();
+    let radius = point_settings.point_size / 2.;
+    let on_plane = |rig: &GlobalTransform, sensor: Vec2| {
+        rig.transform_point(intrinsics.back_project(sensor, depth))
+    };
+
+    // Points on the plane land exactly where the homography maps them
+    for position in plane.grid() {
+        let world_position = plane_to_world.transform_point(position.extend(0.0));
+        gizmos.sphere(world_position, Quat::default(), radius / 2., color);
+
+        let Some((main, _)) = second.observe(&intrinsics, rig, world_position) else {
+            continue;
+        };
+        gizmos.sphere(on_plane(rig, main), Quat::default(), radius / 2., color);
+        if let Some(mapped) = apply_homography(homography, main) {
+            gizmos.sphere(
+                on_plane(second_rig, mapped),
+                Quat::default(),
+                radius / 2.,
+                color,
+            );
+        }
+    }
+
+    // Points off the plane do not, the difference is their parallax along the epipolar line
+    if !plane.show_parallax {
+        return;
+    }
+    for (transform, WorldPoint { index }) in &points {
+        let Some((main, second)) = second.observe(&intrinsics, rig, transform.translation()) else {
+            continue;
+        };
+        let Some(mapped) = apply_homography(homography, main) else {
+            continue;
+        };
+        let color = cache.color(*index);
+        let mapped = on_plane(second_rig, mapped);
+
+        gizmos.circle(mapped, second_rig.back(), radius, color);
+        gizmos.line(mapped, on_plane(second_rig, second), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn second() -> SecondCamera {
+        SecondCamera {
+            baseline: Vec3::new(0.5, 0.1, -0.2),
+            rotation: Vec3::new(5.0, -10.0, 3.0),
+            ..default()
+        }
+    }
+
+    fn intrinsics() -> CameraIntrinsics {
+        CameraIntrinsics {
+            fx: 1.2,
+            fy: 1.1,
+            cx: 0.1,
+            cy: -0.05,
+            skew: 0.02,
+        }
+    }
+
+    #[test]
+    fn maps_points_on_the_plane_between_cameras() {
+        let (intrinsics, second) = (intrinsics(), second());
+        let normal = Vec3::new(0.2, -0.1, 1.0).normalize();
+        let on_plane = Vec3::new(0.0, 0.0, 4.0);
+        // nᵀX + d = 0, in main camera space
+        let d = -normal.dot(on_plane);
+        let homography = plane_induced_homography(intrinsics.matrix(), &second, normal, d).unwrap();
+
+        let (u, v) = (
+            normal.any_orthonormal_pair().0,
+            normal.any_orthonormal_pair().1,
+        );
+        for (a, b) in [(0.0, 0.0), (1.0, 0.5), (-0.8, 1.2), (0.3, -1.5)] {
+            let point = on_plane + a * u + b * v;
+            let main = intrinsics.project(point).unwrap();
+            let seen = intrinsics
+                .project(second.rotation_matrix() * point + second.translation())
+                .unwrap();
+
+            let mapped = apply_homography(homography, main).unwrap();
+            assert!(mapped.abs_diff_eq(seen, 1e-4), "{mapped} != {seen}");
+        }
+
+        // Off the plane there is parallax
+        let point = on_plane + normal;
+        let main = intrinsics.project(point).unwrap();
+        let seen = intrinsics
+            .project(second.rotation_matrix() * point + second.translation())
+            .unwrap();
+        assert!(apply_homography(homography, main).unwrap().distance(seen) > 1e-2);
+    }
+
+    #[test]
+    fn plane_through_optical_centre_has_no_homography() {
+        assert_eq!(
+            plane_induced_homography(intrinsics().matrix(), &second(), Vec3::Z, 0.0),
+            None
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! A picture on the image planes, which the image points can also be taken from.
+//!
+//! The image is stretched over the sensor, so it should have the same aspect ratio as
+//! [`crate::ImageSize`] to not look squashed.
+
+use bevy::{ecs::system::SystemParam, prelude::*, render::render_resource::TextureFormat};
+use bevy_inspector_egui::{inspector_options::ReflectInspectorOptions, InspectorOptions};
+
+use crate::{
+    coords, material_mesh_cache::MeshMaterialCache, plain_plane_material, point_set::PointRecord,
+    sync_image_planes, ImagePlane,
+};
+
+pub struct PlaneImagePlugin;
+
+impl Plugin for PlaneImagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaneImage>()
+            .register_type::<PlaneImage>()
+            .register_type::<ImageFeatures>()
+            .init_resource::<PlaneImageHandle>()
+            .init_resource::<PlaneImageMaterial>()
+            .add_systems(
+                PreUpdate,
+                (
+                    load_plane_image.run_if(resource_changed::<PlaneImage>),
+                    watch_plane_image,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_plane_image_material.run_if(resource_changed::<PlaneImage>),
+                    apply_plane_materials,
+                )
+                    .chain()
+                    .after(sync_image_planes),
+            );
+    }
+}
+
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct PlaneImage {
+    /// Relative to the assets folder, e.g. `images/scene.jpg`.
+    /// Leave empty for plain planes.
+    path: String,
+
+    #[inspector(min = 0.0, max = 1.0)]
+    opacity: f32,
+
+    /// Also show the image on the deeper planes, scaled with them, instead of only the main one
+    all_planes: bool,
+
+    /// Take the image points from the image instead of [`crate::ImagePoints::distribution`]
+    features: ImageFeatures,
+}
+
+impl Default for PlaneImage {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            opacity: 0.8,
+            all_planes: true,
+            features: ImageFeatures::default(),
+        }
+    }
+}
+
+/// What image points to find in the image
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+pub enum ImageFeatures {
+    #[default]
+    None,
+
+    /// The strongest Shi-Tomasi corners, at most [`crate::ImagePoints::num_points`] of them and
+    /// at least `min_distance` image pixels apart
+    Corners { min_distance: f32 },
+
+    /// The centres of a grid of cells, each colored like the pixel under it
+    PixelGrid { rows: usize, cols: usize },
+}
+
+#[derive(Debug, Default, Resource)]
+struct PlaneImageHandle(Option<Handle<Image>>);
+
+/// The material of the planes showing the image, once there is an image
+#[derive(Debug, Default, Resource)]
+struct PlaneImageMaterial(Option<Handle<StandardMaterial>>);
+
+fn load_plane_image(
+    settings: Res<PlaneImage>,
+    asset_server: Res<AssetServer>,
+    mut handle: ResMut<PlaneImageHandle>,
+) {
+    let path = settings.path.trim();
+    let new = (!path.is_empty()).then(|| asset_server.load::<Image>(path.to_string()));
+
+    // Avoid flagging a change (and so resampling) when only e.g. the opacity changed
+    if handle.0 != new {
+        handle.0 = new;
+    }
+}
+
+/// Flag the handle as changed whenever the image (re)loads, which includes edits on disk
+fn watch_plane_image(
+    mut events: EventReader<AssetEvent<Image>>,
+    mut handle: ResMut<PlaneImageHandle>,
+) {
+    let Some(id) = handle.0.as_ref().map(Handle::id) else {
+        events.clear();
+        return;
+    };
+
+    let reloaded = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id: event_id }
+        | AssetEvent::Modified { id: event_id }
+        | AssetEvent::Removed { id: event_id } => *event_id == id,
+        _ => false,
+    });
+    if reloaded {
+        handle.set_changed();
+    }
+}
+
+fn update_plane_image_material(
+    settings: Res<PlaneImage>,
+    handle: Res<PlaneImageHandle>,
+    mut material: ResMut<PlaneImageMaterial>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(image) = handle.0.clone() else {
+        material.0 = None;
+        return;
+    };
+
+    let image_material = StandardMaterial {
+        base_color: Color::WHITE.with_alpha(settings.opacity),
+        base_color_texture: Some(image),
+        alpha_mode: AlphaMode::Blend,
+        // Show the image as is, regardless of the lighting
+        unlit: true,
+        // Visible from behind too, mirrored like it would be on a real sensor
+        cull_mode: None,
+        double_sided: true,
+        ..default()
+    };
+
+    match material
+        .0
+        .as_ref()
+        .and_then(|handle| materials.get_mut(handle))
+    {
+        Some(existing) => *existing = image_material,
+        None => material.0 = Some(materials.add(image_material)),
+    }
+}
+
+/// Give each plane either the image or the plain material
+fn apply_plane_materials(
+    mut cache: MeshMaterialCache,
+    settings: Res<PlaneImage>,
+    image_material: Res<PlaneImageMaterial>,
+    mut planes: Query<(&ImagePlane, &mut Handle<StandardMaterial>)>,
+) {
+    let plain = plain_plane_material(&mut cache);
+
+    for (ImagePlane { index }, mut material) in &mut planes {
+        let wanted = match &image_material.0 {
+            Some(image) if *index == 0 || settings.all_planes => image.clone(),
+            _ => plain.clone(),
+        };
+
+        // Avoid triggering change detection every frame
+        material.set_if_neq(wanted);
+    }
+}
+
+/// The pixels of a loaded image, in the pixel coordinates of [`coords`] but with the
+/// resolution of the image rather than of the sensor
+struct Pixels {
+    size: UVec2,
+    colors: Vec<LinearRgba>,
+}
+
+impl Pixels {
+    /// `None` for (e.g. compressed) formats which cannot be read back
+    fn new(image: &Image) -> Option<Self> {
+        let size = image.size();
+        let format = image.texture_descriptor.format;
+
+        // 8-bit images are taken to be sRGB encoded, like photos are
+        let colors = match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image
+                .data
+                .chunks_exact(4)
+                .map(|p| Color::srgba_u8(p[0], p[1], p[2], p[3]).into())
+                .collect(),
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => image
+                .data
+                .chunks_exact(4)
+                .map(|p| Color::srgba_u8(p[2], p[1], p[0], p[3]).into())
+                .collect(),
+            TextureFormat::R8Unorm => image
+                .data
+                .iter()
+                .map(|&v| Color::srgb_u8(v, v, v).into())
+                .collect(),
+            TextureFormat::Rgba32Float => image
+                .data
+                .chunks_exact(16)
+                .map(|p| {
+                    let [r, g, b, a] = [0, 4, 8, 12]
+                        .map(|i| f32::from_le_bytes([p[i], p[i + 1], p[i + 2], p[i + 3]]));
+                    LinearRgba::new(r, g, b, a)
+                })
+                .collect(),
+            _ => {
+                warn!("cannot take image points from an image in {format:?}");
+                return None;
+            }
+        };
+
+        Some(Self { size, colors })
+    }
+
+    fn color(&self, pixel: UVec2) -> LinearRgba {
+        let pixel = pixel.min(self.size - 1);
+        self.colors[(pixel.y * self.size.x + pixel.x) as usize]
+    }
+
+    /// Cell centres, each with the color under it
+    fn grid(&self, rows: usize, cols: usize) -> Vec<(Vec2, LinearRgba)> {
+        let cells = UVec2::new(cols as u32, rows as u32).max(UVec2::ONE);
+        let cell_size = self.size.as_vec2() / cells.as_vec2();
+
+        (0..cells.y)
+            .flat_map(|row| (0..cells.x).map(move |col| UVec2::new(col, row)))
+            .map(|cell| {
+                // Pixel centres are at integer coordinates
+                let centre = (cell.as_vec2() + 0.5) * cell_size - 0.5;
+                (
+                    centre,
+                    self.color(centre.round().max(Vec2::ZERO).as_uvec2()),
+                )
+            })
+            .collect()
+    }
+
+    /// The `max_corners` strongest Shi-Tomasi corners, strongest first
+    fn corners(&self, max_corners: usize, min_distance: f32) -> Vec<Vec2> {
+        // Sobel gradients, then the smallest eigenvalue of the structure tensor over a 3x3 window
+        let (width, height) = (self.size.x as i32, self.size.y as i32);
+        let index =
+            |x: i32, y: i32| (y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize;
+        let luminance: Vec<f32> = self.colors.iter().map(Luminance::luminance).collect();
+        let l = |x, y| luminance[index(x, y)];
+
+        let mut gradients = vec![Vec2::ZERO; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let gx = (l(x + 1, y - 1) + 2. * l(x + 1, y) + l(x + 1, y + 1))
+                    - (l(x - 1, y - 1) + 2. * l(x - 1, y) + l(x - 1, y + 1));
+                let gy = (l(x - 1, y + 1) + 2. * l(x, y + 1) + l(x + 1, y + 1))
+                    - (l(x - 1, y - 1) + 2. * l(x, y - 1) + l(x + 1, y - 1));
+                gradients[index(x, y)] = Vec2::new(gx, gy);
+            }
+        }
+
+        let mut response = vec![0.0; gradients.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let (mut xx, mut xy, mut yy) = (0.0, 0.0, 0.0);
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let g = gradients[index(x + dx, y + dy)];
+                        xx += g.x * g.x;
+                        xy += g.x * g.y;
+                        yy += g.y * g.y;
+                    }
+                }
+                let half_trace = (xx + yy) / 2.;
+                let det = xx * yy - xy * xy;
+                response[index(x, y)] =
+                    half_trace - (half_trace * half_trace - det).max(0.0).sqrt();
+            }
+        }
+
+        // Local maxima which are not negligible compared to the strongest corner
+        let threshold = response.iter().copied().fold(0.0, f32::max) * 0.01;
+        let mut candidates: Vec<(IVec2, f32)> = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let r = response[index(x, y)];
+                let is_maximum = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .all(|(dx, dy)| response[index(x + dx, y + dy)] <= r);
+                if r > threshold && is_maximum {
+                    candidates.push((IVec2::new(x, y), r));
+                }
+            }
+        }
+        candidates.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let mut corners: Vec<Vec2> = Vec::new();
+        for (pixel, _) in candidates {
+            if corners.len() >= max_corners {
+                break;
+            }
+            let pixel = pixel.as_vec2();
+            if corners.iter().all(|c| c.distance(pixel) >= min_distance) {
+                corners.push(pixel);
+            }
+        }
+
+        corners
+    }
+}
+
+/// Image points found in the [`PlaneImage`], see [`ImageFeatures`]
+#[derive(SystemParam)]
+pub struct PlaneImageFeatures<'w> {
+    settings: Res<'w, PlaneImage>,
+    handle: Res<'w, PlaneImageHandle>,
+    images: Res<'w, Assets<Image>>,
+}
+
+impl PlaneImageFeatures<'_> {
+    /// Whether the features may have changed, including when only e.g. the opacity did
+    pub fn is_changed(&self) -> bool {
+        self.handle.is_changed() || self.settings.is_changed()
+    }
+
+    /// Whether the image itself changed. Changes to [`ImageFeatures`] are left to the caller,
+    /// see [`Self::features`].
+    pub fn is_reloaded(&self) -> bool {
+        self.handle.is_changed()
+    }
+
+    pub fn features(&self) -> &ImageFeatures {
+        &self.settings.features
+    }
+
+    /// `None` when no features are wanted, or there is no image loaded (yet) to find them in
+    pub fn points(&self, max_points: usize, size: Vec2) -> Option<Vec<PointRecord>> {
+        if self.settings.features == ImageFeatures::None {
+            return None;
+        }
+        let image = self.images.get(self.handle.0.as_ref()?)?;
+        let pixels = Pixels::new(image)?;
+        let to_metric = |pixel: Vec2| coords::pixel_to_metric(pixel, size, pixels.size);
+
+        let points = match self.settings.features {
+            ImageFeatures::None => return None,
+            ImageFeatures::Corners { min_distance } => pixels
+                .corners(max_points, min_distance)
+                .into_iter()
+                .map(|pixel| PointRecord {
+                    position: to_metric(pixel),
+                    label: None,
+                    group: None,
+                    color: None,
+                })
+                .collect(),
+            ImageFeatures::PixelGrid { rows, cols } => pixels
+                .grid(rows, cols)
+                .into_iter()
+                .map(|(pixel, color)| PointRecord {
+                    position: to_metric(pixel),
+                    label: None,
+                    group: None,
+                    color: Some(color.with_alpha(1.0).into()),
+                })
+                .collect(),
+        };
+
+        Some(points)
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+/// How image points are laid out on the sensor.
+/// Positions are in metric sensor coordinates, see [`crate::coords`].
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+pub enum PointDistribution {
+    /// Independent uniformly random points
+    #[default]
+    Uniform,
+
+    /// Points in the centres of a `rows` by `cols` grid, ignoring the number of points
+    Grid { rows: usize, cols: usize },
+
+    /// Random points which are at least `min_distance` apart.
+    /// May give fewer points than asked for if they do not fit.
+    PoissonDisk { min_distance: f32 },
+
+    /// The Halton sequence in bases 2 and 3
+    Halton,
+
+    /// The Sobol sequence
+    Sobol,
+
+    /// Evenly spaced on the boundary of an ellipse around the centre of the sensor.
+    /// Equal half sizes give a circle.
+    Ellipse { half_size: Vec2 },
+
+    /// Evenly spaced from `start` to `end`, both included
+    Line { start: Vec2, end: Vec2 },
+}
+
+/// Candidates tried around each active point before it is retired, see Bridson (2007)
+const POISSON_DISK_ATTEMPTS: usize = 30;
+
+impl PointDistribution {
+    /// `num_points` positions on a sensor of the given size
+    pub fn sample(&self, num_points: usize, size: Vec2, rng: &mut impl Rng) -> Vec<Vec2> {
+        // Unit square to the sensor
+        let to_sensor = |unit: Vec2| (unit - 0.5) * size;
+
+        match self {
+            Self::Uniform => {
+                let rect = Rectangle::from_size(size);
+                (0..num_points)
+                    .map(|_| rect.sample_interior(&mut *rng))
+                    .collect()
+            }
+            Self::Grid { rows, cols } => (0..*rows)
+                .flat_map(|row| (0..*cols).map(move |col| (row, col)))
+                .map(|(row, col)| {
+                    to_sensor(Vec2::new(
+                        (col as f32 + 0.5) / *cols as f32,
+                        // Row 0 at the top
+                        1.0 - (row as f32 + 0.5) / *rows as f32,
+                    ))
+                })
+                .collect(),
+            Self::PoissonDisk { min_distance } => {
+                poisson_disk(num_points, size, *min_distance, rng)
+                    .into_iter()
+                    .map(to_sensor)
+                    .collect()
+            }
+            // Skip the first element, which is the corner of the sensor for both sequences
+            Self::Halton => (1..=num_points as u32)
+                .map(|i| to_sensor(Vec2::new(radical_inverse(i, 2), radical_inverse(i, 3))))
+                .collect(),
+            Self::Sobol => sobol(num_points + 1)
+                .into_iter()
+                .skip(1)
+                .map(to_sensor)
+                .collect(),
+            Self::Ellipse { half_size } => (0..num_points)
+                .map(|i| {
+                    let angle = std::f32::consts::TAU * i as f32 / num_points as f32;
+                    Vec2::from_angle(angle) * *half_size
+                })
+                .collect(),
+            Self::Line { start, end } => {
+                let steps = num_points.saturating_sub(1).max(1) as f32;
+                (0..num_points)
+                    .map(|i| start.lerp(*end, i as f32 / steps))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The digits of `i` in `base`, mirrored around the decimal point
+fn radical_inverse(mut i: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut digit_weight = 1.0 / base as f32;
+
+    while i > 0 {
+        result += (i % base) as f32 * digit_weight;
+        i /= base;
+        digit_weight /= base as f32;
+    }
+
+    result
+}
+
+/// The first `n` points of the 2D Sobol sequence in the unit square, in Gray code order.
+/// The first dimension is the van der Corput sequence, the second uses the primitive polynomial
+/// x + 1.
+fn sobol(n: usize) -> Vec<Vec2> {
+    const BITS: usize = 32;
+
+    let mut directions = [[0u32; BITS]; 2];
+    for bit in 0..BITS {
+        directions[0][bit] = 1 << (BITS - 1 - bit);
+        directions[1][bit] = if bit == 0 {
+            1 << (BITS - 1)
+        } else {
+            directions[1][bit - 1] ^ (directions[1][bit - 1] >> 1)
+        };
+    }
+
+    let mut x = [0u32; 2];
+    let mut points = Vec::with_capacity(n);
+    for i in 0..n {
+        if i > 0 {
+            // Gray code: flip the direction of the lowest zero bit of i - 1
+            let bit = (i - 1).trailing_ones() as usize;
+            x[0] ^= directions[0][bit];
+            x[1] ^= directions[1][bit];
+        }
+        points.push(Vec2::new(x[0] as f32, x[1] as f32) / 2f32.powi(BITS as i32));
+    }
+
+    points
+}
+
+/// Bridson's algorithm in a rectangle of the given size, since distances are in sensor units.
+/// Returns points in the unit square.
+fn poisson_disk(num_points: usize, size: Vec2, min_distance: f32, rng: &mut impl Rng) -> Vec<Vec2> {
+    if num_points == 0 || min_distance <= 0.0 || size.min_element() <= 0.0 {
+        return Vec::new();
+    }
+
+    // A background grid with at most one point per cell
+    let cell_size = min_distance / std::f32::consts::SQRT_2;
+    let dims = (size / cell_size).ceil().as_uvec2().max(UVec2::ONE);
+    let cell_of = |p: Vec2| (p / cell_size).as_uvec2().min(dims - 1);
+    let mut grid: Vec<Option<usize>> = vec![None; (dims.x * dims.y) as usize];
+
+    let mut points = vec![Vec2::new(
+        rng.gen_range(0.0..size.x),
+        rng.gen_range(0.0..size.y),
+    )];
+    let mut active = vec![0];
+    let cell = cell_of(points[0]);
+    grid[(cell.y * dims.x + cell.x) as usize] = Some(0);
+
+    while !active.is_empty() && points.len() < num_points {
+        let active_index = rng.gen_range(0..active.len());
+        let centre = points[active[active_index]];
+
+        let candidate = (0..POISSON_DISK_ATTEMPTS)
+            .map(|_| {
+                let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                let distance = rng.gen_range(min_distance..2.0 * min_distance);
+                centre + Vec2::from_angle(angle) * distance
+            })
+            .find(|candidate| {
+                if candidate.cmplt(Vec2::ZERO).any() || candidate.cmpge(size).any() {
+                    return false;
+                }
+
+                // Neighbours within min_distance are at most two cells away
+                let cell = cell_of(*candidate).as_ivec2();
+                (-2..=2)
+                    .flat_map(|dy| (-2..=2).map(move |dx| cell + IVec2::new(dx, dy)))
+                    .filter(|c| c.cmpge(IVec2::ZERO).all() && c.cmplt(dims.as_ivec2()).all())
+                    .filter_map(|c| grid[(c.y as u32 * dims.x + c.x as u32) as usize])
+                    .all(|other| points[other].distance(*candidate) >= min_distance)
+            });
+
+        match candidate {
+            Some(candidate) => {
+                let cell = cell_of(candidate);
+                grid[(cell.y * dims.x + cell.x) as usize] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+            }
+            None => {
+                active.swap_remove(active_index);
+            }
+        }
+    }
+
+    points.into_iter().map(|p| p / size).collect()
+}
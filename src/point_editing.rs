@@ -0,0 +1,234 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_mod_picking::{
+    events::{Click, Down, Drag, Pointer},
+    pointer::{PointerButton, PointerId},
+};
+
+use crate::{
+    camera::{CameraIntrinsics, CameraModel},
+    image_shapes::is_placing_shape,
+    material_mesh_cache::MeshMaterialCache,
+    next_point_index,
+    point_set::PointRecord,
+    spawn_image_point, sync_sub_points,
+    triangulation::is_picking_correspondence,
+    viewport_camera::PointerCameras,
+    ImagePlane, ImagePlanes, ImagePoint, ImagePointIndex, ImagePoints, ImageSize, MainImagePlane,
+    MainPointsCommands, SensorPosition, SubImagePoint,
+};
+
+/// Left click the main image plane to place an [`ImagePoint`], right click a point to delete it,
+/// and drag points to move them
+pub struct PointEditingPlugin;
+
+impl Plugin for PointEditingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MainPlaneClick>()
+            .init_resource::<PointerTravel>()
+            .add_systems(
+                Update,
+                (
+                    track_pointer_travel,
+                    detect_main_plane_clicks,
+                    place_points
+                        .run_if(not(is_placing_shape).and_then(not(is_picking_correspondence))),
+                    delete_points,
+                    drag_image_points,
+                )
+                    .chain()
+                    .before(sync_sub_points),
+            );
+    }
+}
+
+/// A left click on the main image plane, from either camera
+#[derive(Debug, Event)]
+pub struct MainPlaneClick {
+    /// Where on the plane, in normalized image coordinates
+    pub normalized: Vec2,
+}
+
+/// How far (in logical pixels) the pointer may move while pressed for it to still be a click.
+/// Otherwise orbiting the camera over the plane would place points.
+const CLICK_SLOP: f32 = 4.0;
+
+/// How far each pointer has moved since each of its buttons was pressed
+#[derive(Debug, Default, Resource)]
+pub struct PointerTravel(HashMap<(PointerId, PointerButton), f32>);
+
+impl PointerTravel {
+    /// Whether the release of this button was a click rather than the end of a drag
+    pub fn is_click(&self, pointer: PointerId, button: PointerButton) -> bool {
+        !self
+            .0
+            .get(&(pointer, button))
+            .is_some_and(|travel| *travel > CLICK_SLOP)
+    }
+}
+
+fn track_pointer_travel(
+    mut travel: ResMut<PointerTravel>,
+    mut downs: EventReader<Pointer<Down>>,
+    mut drags: EventReader<Pointer<Drag>>,
+) {
+    for down in downs.read() {
+        travel.0.insert((down.pointer_id, down.event.button), 0.0);
+    }
+
+    for drag in drags.read() {
+        let entry = travel
+            .0
+            .entry((drag.pointer_id, drag.event.button))
+            .or_default();
+        *entry = entry.max(drag.event.distance.length());
+    }
+}
+
+pub(crate) fn detect_main_plane_clicks(
+    travel: Res<PointerTravel>,
+    mut clicks: EventReader<Pointer<Click>>,
+    mut main_plane_clicks: EventWriter<MainPlaneClick>,
+    main_image_plane: Query<&GlobalTransform, (With<ImagePlane>, With<MainImagePlane>)>,
+) {
+    for click in clicks.read() {
+        if click.event.button != PointerButton::Primary
+            || !travel.is_click(click.pointer_id, click.event.button)
+        {
+            continue;
+        }
+        let (Ok(plane), Some(pos)) = (main_image_plane.get(click.target), click.hit.position)
+        else {
+            continue;
+        };
+
+        // The plane mesh is in normalized image coordinates, see `sync_image_planes`
+        let normalized = plane.affine().inverse().transform_point3(pos).xy();
+        main_plane_clicks.send(MainPlaneClick { normalized });
+    }
+}
+
+fn place_points(
+    mut commands: MainPointsCommands,
+    mut cache: MeshMaterialCache,
+    mut clicks: EventReader<MainPlaneClick>,
+    camera: CameraModel,
+    planes: Res<ImagePlanes>,
+    settings: Res<ImagePoints>,
+    points: Query<&ImagePointIndex, With<ImagePoint>>,
+) {
+    let indices = next_point_index(&points)..;
+
+    for (index, MainPlaneClick { normalized }) in indices.zip(clicks.read()) {
+        let position = camera.intrinsics.denormalize(*normalized);
+        let transform =
+            Transform::from_translation(camera.back_project(position, planes.main_depth()))
+                .with_scale(Vec3::splat(settings.point_size));
+
+        spawn_image_point(
+            &mut commands,
+            &mut cache,
+            index,
+            PointRecord {
+                position,
+                label: None,
+                group: None,
+                color: None,
+            },
+            transform,
+        );
+    }
+}
+
+/// Right clicking a point or any of its sub-points deletes all of them
+fn delete_points(
+    mut commands: Commands,
+    travel: Res<PointerTravel>,
+    mut clicks: EventReader<Pointer<Click>>,
+    // Only image points and their sub-points have these
+    points: Query<(Entity, &ImagePointIndex)>,
+) {
+    for click in clicks.read() {
+        if click.event.button != PointerButton::Secondary
+            || !travel.is_click(click.pointer_id, click.event.button)
+        {
+            continue;
+        }
+        let Ok((_, clicked)) = points.get(click.target) else {
+            continue;
+        };
+
+        for (entity, index) in &points {
+            if index.index == clicked.index {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Hold to drag sub-points on their own plane, see [`drag_image_points`]
+const DRAG_SUB_POINT_KEYS: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::ControlRight];
+
+/// Slide image points within the main image plane, staying on the sensor.
+///
+/// With control held, sub-points can be dragged on their own plane too. The image point then
+/// moves to where the ray through the sub-point meets the main image plane, which is the same
+/// normalized position.
+#[allow(clippy::too_many_arguments)]
+fn drag_image_points(
+    mut drag_events: EventReader<Pointer<Drag>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    cameras: PointerCameras,
+    size: Res<ImageSize>,
+    intrinsics: Res<CameraIntrinsics>,
+    dragged: Query<(&ImagePointIndex, Option<&SubImagePoint>)>,
+    planes: Query<(&ImagePlane, &Transform, &GlobalTransform), Without<ImagePoint>>,
+    mut points: Query<(&ImagePointIndex, &mut SensorPosition, &mut Transform), With<ImagePoint>>,
+) {
+    let find_plane = |index: usize| planes.iter().find(|(plane, ..)| plane.index == index);
+
+    for drag in drag_events.read() {
+        let Ok((ImagePointIndex { index }, sub_point)) = dragged.get(drag.target) else {
+            continue;
+        };
+        let plane_index = match sub_point {
+            None => 0,
+            Some(SubImagePoint { plane_index }) if keys.any_pressed(DRAG_SUB_POINT_KEYS) => {
+                *plane_index
+            }
+            Some(_) => continue,
+        };
+        let (Some((_, _, plane)), Some((_, main_plane, _))) =
+            (find_plane(plane_index), find_plane(0))
+        else {
+            continue;
+        };
+
+        let Some(ray) = cameras.ray(&drag.pointer_location) else {
+            continue;
+        };
+        let Some(distance) =
+            ray.intersect_plane(plane.translation(), InfinitePlane3d::new(plane.back()))
+        else {
+            continue;
+        };
+
+        // The plane meshes are in normalized image coordinates, see `sync_image_planes`
+        let normalized = plane
+            .affine()
+            .inverse()
+            .transform_point3(ray.get_point(distance))
+            .xy();
+        let half = **size / 2.;
+        let sensor = intrinsics.denormalize(normalized).clamp(-half, half);
+
+        for (point_index, mut sensor_position, mut transform) in &mut points {
+            if point_index.index != *index {
+                continue;
+            }
+
+            sensor_position.0 = sensor;
+            transform.translation =
+                main_plane.transform_point(intrinsics.normalize(sensor).extend(0.0));
+        }
+    }
+}
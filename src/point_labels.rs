@@ -0,0 +1,384 @@
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        view::{NoFrustumCulling, RenderLayers, VisibilitySystems},
+    },
+    text::{update_text2d_layout, Text2dBounds, TextLayoutInfo},
+    transform::TransformSystem,
+    utils::{HashMap, HashSet},
+    window::PrimaryWindow,
+};
+use bevy_inspector_egui::egui;
+use bevy_mod_picking::prelude::Pickable;
+
+use crate::{
+    material_mesh_cache::MeshMaterialCache, ImagePoint, ImagePointIndex, ImagePoints, MainCamera,
+    PointGroup, PointLabel, PointMaterial, SecondaryCamera, SubImagePoint, MISC_LAYER,
+    SECONDARY_LAYER,
+};
+
+/// Text billboards next to image points (and their sub-points), and showing or hiding groups of
+/// points
+pub struct PointLabelsPlugin;
+
+impl Plugin for PointLabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LabelSettings>()
+            .register_type::<LabelSettings>()
+            .init_resource::<PointGroups>()
+            .add_systems(Update, apply_group_visibility)
+            .add_systems(
+                PostUpdate,
+                (
+                    // After points are spawned and despawned in Update, so labels are laid out,
+                    // meshed and placed in the same frame as their points
+                    sync_point_labels
+                        .before(update_text2d_layout)
+                        .before(TransformSystem::TransformPropagate),
+                    mesh_point_labels.after(update_text2d_layout),
+                    face_point_labels
+                        .after(TransformSystem::TransformPropagate)
+                        .before(VisibilitySystems::CheckVisibility),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct LabelSettings {
+    show_labels: bool,
+    label_sub_points: bool,
+    /// Label points without a label of their own A, B, C, ... by index
+    auto_labels: bool,
+}
+
+impl Default for LabelSettings {
+    fn default() -> Self {
+        Self {
+            show_labels: true,
+            label_sub_points: true,
+            auto_labels: false,
+        }
+    }
+}
+
+/// Which groups of points are hidden, see [`PointGroup`]
+#[derive(Debug, Default, Resource)]
+pub struct PointGroups {
+    hidden: HashSet<String>,
+}
+
+impl PointGroups {
+    fn is_visible(&self, group: Option<&PointGroup>) -> bool {
+        !group.is_some_and(|group| self.hidden.contains(&**group))
+    }
+}
+
+/// A checkbox for each group which currently has points
+pub fn ui_point_groups(world: &mut World, ui: &mut egui::Ui) {
+    let mut groups: Vec<String> = world
+        .query::<&PointGroup>()
+        .iter(world)
+        .map(|group| group.0.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    groups.sort();
+
+    ui.collapsing("Point groups", |ui| {
+        if groups.is_empty() {
+            ui.label("No points are in a group");
+        }
+
+        let mut point_groups = world.resource_mut::<PointGroups>();
+        for group in groups {
+            let mut visible = !point_groups.hidden.contains(&group);
+            if ui.checkbox(&mut visible, &group).changed() {
+                if visible {
+                    point_groups.hidden.remove(&group);
+                } else {
+                    point_groups.hidden.insert(group);
+                }
+            }
+        }
+    });
+}
+
+/// "A" to "Z", then "AA", "AB", ... like spreadsheet columns
+fn letter_label(index: usize) -> String {
+    let mut label = Vec::new();
+    let mut n = index + 1;
+
+    while n > 0 {
+        n -= 1;
+        label.push(b'A' + (n % 26) as u8);
+        n /= 26;
+    }
+    label.reverse();
+
+    String::from_utf8(label).unwrap()
+}
+
+fn apply_group_visibility(
+    groups: Res<PointGroups>,
+    image_points: Query<(&ImagePointIndex, Option<&PointGroup>), With<ImagePoint>>,
+    mut points: Query<(&ImagePointIndex, &mut Visibility)>,
+) {
+    let hidden: HashSet<usize> = image_points
+        .iter()
+        .filter(|(_, group)| !groups.is_visible(*group))
+        .map(|(index, _)| index.index)
+        .collect();
+
+    for (index, mut visibility) in &mut points {
+        let wanted = if hidden.contains(&index.index) {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+
+        // Avoid triggering change detection every frame
+        visibility.set_if_neq(wanted);
+    }
+}
+
+/// One of the copies of a label, each facing its own camera and only rendered by that camera
+#[derive(Debug, Clone, Copy, Component)]
+enum LabelView {
+    Main,
+    Secondary,
+}
+
+impl LabelView {
+    fn render_layers(self) -> RenderLayers {
+        RenderLayers::layer(match self {
+            LabelView::Main => MISC_LAYER,
+            LabelView::Secondary => SECONDARY_LAYER,
+        })
+    }
+}
+
+/// The text of a label, laid out by `bevy_text` without being drawn as 2D text.
+/// A child of the point it labels, with a [`LabelView`] per camera as its children.
+#[derive(Debug, Component)]
+struct PointLabelText;
+
+/// Size of the font atlas the glyphs are rendered into, in logical pixels
+const LABEL_FONT_SIZE: f32 = 32.0;
+
+/// Height of a line of text, relative to the point size
+const LABEL_HEIGHT: f32 = 1.5;
+
+/// How far the text sits up and to the right of the centre of a point, relative to its size
+const LABEL_OFFSET: f32 = 0.6;
+
+/// Keep a label next to each labelled point and, if enabled, its sub-points
+fn sync_point_labels(
+    mut commands: Commands,
+    mut cache: MeshMaterialCache,
+    settings: Res<LabelSettings>,
+    image_points: Query<(&ImagePointIndex, Option<&PointLabel>, &PointMaterial), With<ImagePoint>>,
+    points: Query<(Entity, &ImagePointIndex, Has<SubImagePoint>)>,
+    mut labels: Query<(Entity, &Parent, &mut Text), With<PointLabelText>>,
+) {
+    let texts: HashMap<_, _> = image_points
+        .iter()
+        .filter_map(|(index, label, material)| {
+            let text = match label {
+                Some(label) => label.0.clone(),
+                None if settings.auto_labels => letter_label(index.index),
+                None => return None,
+            };
+            Some((index.index, (text, cache.color(**material))))
+        })
+        .collect();
+
+    let mut wanted: HashMap<Entity, (String, Color)> = points
+        .iter()
+        .filter(|(_, _, is_sub_point)| {
+            settings.show_labels && (!is_sub_point || settings.label_sub_points)
+        })
+        .filter_map(|(entity, index, _)| Some((entity, texts.get(&index.index)?.clone())))
+        .collect();
+
+    for (entity, parent, mut text) in &mut labels {
+        let Some((value, color)) = wanted.remove(&parent.get()) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        // Only touch the text when it differs, as that lays it out again
+        let section = &text.sections[0];
+        if section.value != value || section.style.color != color {
+            *text = label_text(value, color);
+        }
+    }
+
+    for (point, (value, color)) in wanted {
+        commands.entity(point).with_children(|b| {
+            b.spawn((
+                SpatialBundle::default(),
+                label_text(value, color),
+                Text2dBounds::UNBOUNDED,
+                TextLayoutInfo::default(),
+                PointLabelText,
+                Name::new("Point label"),
+            ))
+            .with_children(|b| {
+                for view in [LabelView::Main, LabelView::Secondary] {
+                    b.spawn((
+                        MaterialMeshBundle::<StandardMaterial>::default(),
+                        view.render_layers(),
+                        view,
+                        // The mesh changes with the text, but its bounds would not
+                        NoFrustumCulling,
+                        NotShadowCaster,
+                        Pickable::IGNORE,
+                    ));
+                }
+            });
+        });
+    }
+}
+
+fn label_text(value: String, color: Color) -> Text {
+    Text::from_section(
+        value,
+        TextStyle {
+            font_size: LABEL_FONT_SIZE,
+            color,
+            ..default()
+        },
+    )
+    .with_no_wrap()
+}
+
+/// A quad per glyph, with the text one unit high and its bottom left at the origin
+fn label_mesh(
+    layout: &TextLayoutInfo,
+    atlases: &Assets<TextureAtlasLayout>,
+    scale_factor: f32,
+) -> Option<(Mesh, Handle<Image>)> {
+    // All glyphs come from one atlas texture, unless the font atlas has filled up
+    let texture = layout.glyphs.first()?.atlas_info.texture.clone();
+    let unit = (LABEL_FONT_SIZE * scale_factor).recip();
+
+    let (mut positions, mut uvs, mut indices) = (Vec::new(), Vec::new(), Vec::new());
+    for glyph in &layout.glyphs {
+        let info = &glyph.atlas_info;
+        if info.texture != texture {
+            continue;
+        }
+        let Some(atlas) = atlases.get(&info.texture_atlas) else {
+            continue;
+        };
+        let rect = atlas.textures[info.glyph_index].as_rect();
+        let (uv_min, uv_max) = (
+            rect.min / atlas.size.as_vec2(),
+            rect.max / atlas.size.as_vec2(),
+        );
+        let (min, max) = (
+            (glyph.position - glyph.size / 2.) * unit,
+            (glyph.position + glyph.size / 2.) * unit,
+        );
+
+        let start = positions.len() as u32;
+        // Glyph positions have Y up, while the atlas has it down
+        positions.extend([
+            [min.x, min.y, 0.0],
+            [max.x, min.y, 0.0],
+            [max.x, max.y, 0.0],
+            [min.x, max.y, 0.0],
+        ]);
+        uvs.extend([
+            [uv_min.x, uv_max.y],
+            [uv_max.x, uv_max.y],
+            [uv_max.x, uv_min.y],
+            [uv_min.x, uv_min.y],
+        ]);
+        indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices));
+
+    Some((mesh, texture))
+}
+
+/// Turn labels which were laid out again into meshes, textured with the font atlas
+fn mesh_point_labels(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    atlases: Res<Assets<TextureAtlasLayout>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    labels: Query<(&Text, &TextLayoutInfo, &Children), Changed<TextLayoutInfo>>,
+    mut views: Query<(&mut Handle<Mesh>, &mut Handle<StandardMaterial>), With<LabelView>>,
+) {
+    // The glyphs are laid out in physical pixels, like `update_text2d_layout` does
+    let scale_factor = windows
+        .get_single()
+        .map(|window| window.resolution.scale_factor())
+        .unwrap_or(1.0);
+
+    for (text, layout, children) in &labels {
+        let Some((mesh, texture)) = label_mesh(layout, &atlases, scale_factor) else {
+            continue;
+        };
+        let mesh = meshes.add(mesh);
+        let material = materials.add(StandardMaterial {
+            base_color: text.sections[0].style.color,
+            base_color_texture: Some(texture),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+
+        let mut views = views.iter_many_mut(children);
+        while let Some((mut view_mesh, mut view_material)) = views.fetch_next() {
+            *view_mesh = mesh.clone();
+            *view_material = material.clone();
+        }
+    }
+}
+
+/// Turn each copy of a label towards its camera, next to the point.
+/// Runs after transform propagation, so it overrides where the label would be relative to the
+/// point, and the label keeps its size in the world however the point is scaled.
+fn face_point_labels(
+    point_settings: Res<ImagePoints>,
+    main_camera: Query<&GlobalTransform, (With<MainCamera>, Without<LabelView>)>,
+    secondary_camera: Query<&GlobalTransform, (With<SecondaryCamera>, Without<LabelView>)>,
+    labels: Query<&GlobalTransform, (With<PointLabelText>, Without<LabelView>)>,
+    mut views: Query<(&mut GlobalTransform, &LabelView, &Parent)>,
+) {
+    let size = point_settings.point_size;
+
+    for (mut transform, view, parent) in &mut views {
+        let camera = match view {
+            LabelView::Main => main_camera.get_single(),
+            LabelView::Secondary => secondary_camera.get_single(),
+        };
+        let (Ok(camera), Ok(point)) = (camera, labels.get(parent.get())) else {
+            continue;
+        };
+        let (_, rotation, _) = camera.to_scale_rotation_translation();
+
+        *transform = GlobalTransform::from(Transform {
+            translation: point.translation()
+                + rotation * Vec3::new(1.0, 1.0, 0.0) * size * LABEL_OFFSET,
+            rotation,
+            scale: Vec3::splat(size * LABEL_HEIGHT),
+        });
+    }
+}
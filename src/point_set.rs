@@ -0,0 +1,447 @@
+//! Image points loaded from a file instead of generated, so exercises can use prepared data.
+//!
+//! Supported formats:
+//! - CSV: One point per row with columns `x, y, label, color, group`, of which all but the first
+//!   two may be left out. An optional header row may give the columns in any order. Lines
+//!   starting with `#` are skipped.
+//! - JSON/RON: A list of `{ x, y, label, color, group }` objects, again with only `x` and `y`
+//!   required.
+//!
+//! Colors are hex strings such as `#ff8800`.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    ecs::system::SystemParam,
+    prelude::*,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{coords, ImageResolution};
+
+pub struct PointSetPlugin;
+
+impl Plugin for PointSetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<PointSet>()
+            .init_asset_loader::<PointSetLoader>()
+            .init_resource::<PointSetSource>()
+            .register_type::<PointSetSource>()
+            .register_type::<PointUnits>()
+            .init_resource::<PointSetHandle>()
+            .add_systems(
+                PreUpdate,
+                (
+                    load_point_set.run_if(resource_changed::<PointSetSource>),
+                    watch_point_set,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// A point on the sensor, with how it should be shown
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointRecord {
+    /// In the units of the file within a [`PointSet`], see [`PointUnits`].
+    /// Metric once it comes out of [`LoadedPointSet`].
+    pub position: Vec2,
+    pub label: Option<String>,
+    pub group: Option<String>,
+    pub color: Option<Color>,
+}
+
+#[derive(Debug, Clone, Asset, TypePath)]
+pub struct PointSet {
+    pub points: Vec<PointRecord>,
+}
+
+#[derive(Debug, Error)]
+pub enum PointSetLoaderError {
+    #[error("could not read the point set: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("the point set is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+
+    #[error("line {line}: {message}")]
+    Csv { line: usize, message: String },
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Ron(#[from] ron::error::SpannedError),
+
+    #[error("invalid color {0:?}, expected a hex color like #ff8800")]
+    Color(String),
+
+    #[error("unsupported point set extension {0:?}")]
+    Extension(String),
+}
+
+#[derive(Default)]
+struct PointSetLoader;
+
+impl AssetLoader for PointSetLoader {
+    type Asset = PointSet;
+    type Settings = ();
+    type Error = PointSetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<PointSet, PointSetLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text = std::str::from_utf8(&bytes)?;
+
+        let extension = load_context
+            .path()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let points = match extension.as_str() {
+            "csv" => parse_csv(text)?,
+            "json" => serde_json::from_str::<Vec<PointEntry>>(text)?
+                .into_iter()
+                .map(PointEntry::into_record)
+                .collect::<Result<_, _>>()?,
+            "ron" => ron::from_str::<Vec<PointEntry>>(text)?
+                .into_iter()
+                .map(PointEntry::into_record)
+                .collect::<Result<_, _>>()?,
+            _ => return Err(PointSetLoaderError::Extension(extension)),
+        };
+
+        Ok(PointSet { points })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv", "json", "ron"]
+    }
+}
+
+/// A point as written in JSON/RON
+#[derive(Debug, Deserialize)]
+struct PointEntry {
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default)]
+    group: Option<String>,
+}
+
+impl PointEntry {
+    fn into_record(self) -> Result<PointRecord, PointSetLoaderError> {
+        Ok(PointRecord {
+            position: Vec2::new(self.x, self.y),
+            label: self.label,
+            group: self.group,
+            color: self.color.as_deref().map(parse_color).transpose()?,
+        })
+    }
+}
+
+fn parse_color(hex: &str) -> Result<Color, PointSetLoaderError> {
+    Srgba::hex(hex.trim())
+        .map(Color::from)
+        .map_err(|_| PointSetLoaderError::Color(hex.to_string()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CsvColumn {
+    X,
+    Y,
+    Label,
+    Color,
+    Group,
+    Ignored,
+}
+
+const DEFAULT_CSV_COLUMNS: [CsvColumn; 5] = [
+    CsvColumn::X,
+    CsvColumn::Y,
+    CsvColumn::Label,
+    CsvColumn::Color,
+    CsvColumn::Group,
+];
+
+fn parse_csv(text: &str) -> Result<Vec<PointRecord>, PointSetLoaderError> {
+    let mut columns: Option<Vec<CsvColumn>> = None;
+    let mut points = Vec::new();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(trimmed);
+        let error = |message: String| PointSetLoaderError::Csv {
+            line: line_number,
+            message,
+        };
+
+        // A header is only allowed before the first point, and is recognised by not being numeric
+        if columns.is_none() && fields[0].trim().parse::<f32>().is_err() {
+            let header = fields
+                .iter()
+                .map(|name| match name.trim().to_lowercase().as_str() {
+                    "x" => CsvColumn::X,
+                    "y" => CsvColumn::Y,
+                    "label" | "name" => CsvColumn::Label,
+                    "color" | "colour" => CsvColumn::Color,
+                    "group" => CsvColumn::Group,
+                    _ => CsvColumn::Ignored,
+                })
+                .collect::<Vec<_>>();
+            if !header.contains(&CsvColumn::X) || !header.contains(&CsvColumn::Y) {
+                return Err(error("the header needs both an x and a y column".into()));
+            }
+            columns = Some(header);
+            continue;
+        }
+        let columns = columns.get_or_insert_with(|| DEFAULT_CSV_COLUMNS.to_vec());
+
+        let (mut x, mut y, mut label, mut color, mut group) = (None, None, None, None, None);
+        for (column, field) in columns.iter().zip(&fields) {
+            let field = field.trim();
+            match column {
+                CsvColumn::X | CsvColumn::Y => {
+                    let value = field
+                        .parse::<f32>()
+                        .map_err(|e| error(format!("invalid coordinate {field:?}: {e}")))?;
+                    if *column == CsvColumn::X {
+                        x = Some(value);
+                    } else {
+                        y = Some(value);
+                    }
+                }
+                CsvColumn::Label if !field.is_empty() => label = Some(field.to_string()),
+                CsvColumn::Color if !field.is_empty() => color = Some(parse_color(field)?),
+                CsvColumn::Group if !field.is_empty() => group = Some(field.to_string()),
+                _ => {}
+            }
+        }
+
+        let (Some(x), Some(y)) = (x, y) else {
+            return Err(error("expected at least an x and a y value".into()));
+        };
+        points.push(PointRecord {
+            position: Vec2::new(x, y),
+            label,
+            group,
+            color,
+        });
+    }
+
+    Ok(points)
+}
+
+/// Split on commas, except within double quotes. A doubled quote is a literal quote.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+
+    fields
+}
+
+/// What the coordinates in a point set file mean, see [`coords`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum PointUnits {
+    #[default]
+    Metric,
+    Pixel,
+    Ndc,
+}
+
+/// Which file to take the image points from
+#[derive(Debug, Default, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct PointSetSource {
+    /// Relative to the assets folder, e.g. `point_sets/example.csv`.
+    /// Leave empty to generate the points instead.
+    path: String,
+    units: PointUnits,
+}
+
+#[derive(Debug, Default, Resource)]
+struct PointSetHandle(Option<Handle<PointSet>>);
+
+fn load_point_set(
+    source: Res<PointSetSource>,
+    asset_server: Res<AssetServer>,
+    mut handle: ResMut<PointSetHandle>,
+) {
+    let path = source.path.trim();
+    let new = (!path.is_empty()).then(|| asset_server.load::<PointSet>(path.to_string()));
+
+    // Units may have changed without the file changing, so this is still a change
+    handle.0 = new;
+}
+
+/// Flag the handle as changed whenever the file (re)loads, which includes edits on disk
+fn watch_point_set(
+    mut events: EventReader<AssetEvent<PointSet>>,
+    mut handle: ResMut<PointSetHandle>,
+) {
+    let Some(id) = handle.0.as_ref().map(Handle::id) else {
+        events.clear();
+        return;
+    };
+
+    let reloaded = events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id: event_id }
+        | AssetEvent::Modified { id: event_id }
+        | AssetEvent::Removed { id: event_id } => *event_id == id,
+        _ => false,
+    });
+    if reloaded {
+        handle.set_changed();
+    }
+}
+
+/// The loaded point set, if there is one, in metric sensor coordinates
+#[derive(SystemParam)]
+pub struct LoadedPointSet<'w> {
+    source: Res<'w, PointSetSource>,
+    handle: Res<'w, PointSetHandle>,
+    assets: Res<'w, Assets<PointSet>>,
+    resolution: Res<'w, ImageResolution>,
+}
+
+impl LoadedPointSet<'_> {
+    pub fn is_changed(&self) -> bool {
+        self.handle.is_changed()
+            || (self.source.units == PointUnits::Pixel && self.resolution.is_changed())
+    }
+
+    /// `None` when no file is set or it is not loaded (yet)
+    pub fn points(&self, size: Vec2) -> Option<Vec<PointRecord>> {
+        let set = self.assets.get(self.handle.0.as_ref()?)?;
+        let to_metric = |position: Vec2| match self.source.units {
+            PointUnits::Metric => position,
+            PointUnits::Pixel => coords::pixel_to_metric(position, size, **self.resolution),
+            PointUnits::Ndc => coords::ndc_to_metric(position, size),
+        };
+
+        Some(
+            set.points
+                .iter()
+                .map(|record| PointRecord {
+                    position: to_metric(record.position),
+                    ..record.clone()
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_of(error: PointSetLoaderError) -> usize {
+        match error {
+            PointSetLoaderError::Csv { line, .. } => line,
+            error => panic!("expected a CSV error, got {error:?}"),
+        }
+    }
+
+    #[test]
+    fn split_quoted_fields() {
+        assert_eq!(
+            split_csv_line(r#"1,2,"left, top",#ff8800"#),
+            ["1", "2", "left, top", "#ff8800"]
+        );
+        assert_eq!(
+            split_csv_line(r#"1,2,"the ""corner""""#),
+            ["1", "2", r#"the "corner""#]
+        );
+        assert_eq!(split_csv_line("1,,3,"), ["1", "", "3", ""]);
+    }
+
+    #[test]
+    fn default_columns_without_header() {
+        let points =
+            parse_csv("# x, y, label, color, group\n0.5, -1, \"a, b\", #ff8800, corners\n2,3\n")
+                .unwrap();
+
+        assert_eq!(
+            points,
+            [
+                PointRecord {
+                    position: Vec2::new(0.5, -1.0),
+                    label: Some("a, b".into()),
+                    group: Some("corners".into()),
+                    color: Some(Color::srgb_u8(0xff, 0x88, 0x00)),
+                },
+                PointRecord {
+                    position: Vec2::new(2.0, 3.0),
+                    label: None,
+                    group: None,
+                    color: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn header_in_any_order() {
+        let points = parse_csv("Group,Y,notes,X\ncorners,2,ignored,1\n,4,,3\n").unwrap();
+
+        assert_eq!(points[0].position, Vec2::new(1.0, 2.0));
+        assert_eq!(points[0].group.as_deref(), Some("corners"));
+        assert_eq!(points[0].label, None);
+        assert_eq!(points[1].position, Vec2::new(3.0, 4.0));
+        assert_eq!(points[1].group, None);
+
+        assert_eq!(line_of(parse_csv("\nx,label\n1,a\n").unwrap_err()), 2);
+    }
+
+    #[test]
+    fn header_only_before_the_first_point() {
+        assert_eq!(line_of(parse_csv("1,2\nx,y\n").unwrap_err()), 2);
+    }
+
+    #[test]
+    fn malformed_rows_are_errors() {
+        assert_eq!(line_of(parse_csv("1,2\n3\n").unwrap_err()), 2);
+        assert_eq!(line_of(parse_csv("x,y\n1,two\n").unwrap_err()), 2);
+        assert!(matches!(
+            parse_csv("1,2,a,#ggg\n"),
+            Err(PointSetLoaderError::Color(color)) if color == "#ggg"
+        ));
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(
+            parse_color(" #00ff00 ").unwrap(),
+            Color::srgb_u8(0, 0xff, 0)
+        );
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("red").is_err());
+    }
+}
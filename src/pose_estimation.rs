@@ -0,0 +1,798 @@
+use bevy::{
+    color::palettes,
+    math::{Affine3A, DMat3, DQuat, DVec2, DVec3},
+    prelude::*,
+};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use thiserror::Error;
+
+use crate::{
+    camera::{CameraIntrinsics, CameraProjection, ProjectionModel},
+    coords, linalg,
+    material_mesh_cache::MeshMaterialCache,
+    projection_matrix::matrix_label,
+    seeded_rng::SeededRng,
+    sensor_corners,
+    sensor_noise::SensorNoise,
+    world_points::WorldPoint,
+    ImagePlanes, ImagePoints, ImageResolution, ImageSize, ParentTransforms,
+};
+
+/// Estimate the pose of the main camera from the world points and where they land on its image
+/// plane (Perspective-n-Point), and compare it to the actual pose
+pub struct PoseEstimationPlugin;
+
+impl Plugin for PoseEstimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PoseEstimation>()
+            .init_resource::<EstimatedPose>()
+            .add_systems(
+                Update,
+                (
+                    ui_pose_estimation,
+                    estimate_camera_pose,
+                    gizmo_pose_estimation,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PoseError {
+    #[error("at least 4 points are needed, got {0}")]
+    TooFewPoints(usize),
+
+    #[error("the points contain non-finite values")]
+    NonFinite,
+
+    #[error("the world points are (nearly) coplanar, which needs a planar variant of EPnP")]
+    Coplanar,
+
+    #[error("the points do not determine a pose")]
+    Degenerate,
+}
+
+/// How the pose is estimated from the correspondences
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PnpMethod {
+    /// Closed form, through the camera space positions of four virtual control points
+    #[default]
+    Epnp,
+
+    /// EPnP, then refined by minimising the reprojection error with Gauss-Newton
+    Iterative,
+}
+
+/// A camera pose as [R|t], mapping world space into camera space like
+/// [`CameraExtrinsics`](crate::camera::CameraExtrinsics) does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub rotation: Mat3,
+    pub translation: Vec3,
+}
+
+impl Pose {
+    pub fn camera_to_world(&self) -> Affine3A {
+        Affine3A::from_mat3_translation(self.rotation, self.translation).inverse()
+    }
+}
+
+/// [R|t] in double precision, while estimating
+type DPose = (DMat3, DVec3);
+
+/// Root mean square distance between the observed and the reprojected normalized image
+/// coordinates, infinite when a point ends up behind the camera
+fn reprojection_error(points: &[(DVec3, DVec2)], (r, t): DPose) -> f64 {
+    let squared: f64 = points
+        .iter()
+        .map(|(world, image)| {
+            let camera = r * *world + t;
+            if camera.z <= f64::EPSILON {
+                return f64::INFINITY;
+            }
+            (camera.truncate() / camera.z).distance_squared(*image)
+        })
+        .sum();
+
+    (squared / points.len() as f64).sqrt()
+}
+
+/// R and t such that camera ≈ R world + t in the least squares sense, using the closed form
+/// with unit quaternions of Horn (1987)
+fn absolute_orientation(world: &[DVec3], camera: &[DVec3]) -> Option<DPose> {
+    let centroid = |points: &[DVec3]| points.iter().sum::<DVec3>() / points.len() as f64;
+    let (world_centroid, camera_centroid) = (centroid(world), centroid(camera));
+
+    // Cross covariance, s[a][b] is the sum of world a times camera b
+    let mut s = [[0.0; 3]; 3];
+    for (w, c) in world.iter().zip(camera) {
+        let (w, c) = (*w - world_centroid, *c - camera_centroid);
+        for (a, row) in s.iter_mut().enumerate() {
+            for (b, entry) in row.iter_mut().enumerate() {
+                *entry += w[a] * c[b];
+            }
+        }
+    }
+    let [[xx, xy, xz], [yx, yy, yz], [zx, zy, zz]] = s;
+    let n = vec![
+        vec![xx + yy + zz, yz - zy, zx - xz, xy - yx],
+        vec![yz - zy, xx - yy - zz, xy + yx, zx + xz],
+        vec![zx - xz, xy + yx, -xx + yy - zz, yz + zy],
+        vec![xy - yx, zx + xz, yz + zy, -xx - yy + zz],
+    ];
+
+    // The rotation is the eigenvector of the largest eigenvalue, as (w, x, y, z)
+    let (_, q) = linalg::symmetric_eigen(&n).pop()?;
+    let rotation = DMat3::from_quat(DQuat::from_xyzw(q[1], q[2], q[3], q[0]).normalize());
+
+    Some((rotation, camera_centroid - rotation * world_centroid))
+}
+
+/// The pose from world points and where they are seen, in normalized image coordinates, using
+/// EPnP, see Lepetit, Moreno-Noguer and Fua (2009).
+///
+/// The points are expressed in four control points, whose camera space positions are a
+/// combination of the null vectors of the projection equations. How much of each is found from
+/// the distances between the control points, which are the same in both spaces.
+pub fn epnp(points: &[(Vec3, Vec2)]) -> Result<Pose, PoseError> {
+    let points = check_points(points)?;
+    let world: Vec<DVec3> = points.iter().map(|(world, _)| *world).collect();
+    let n = world.len() as f64;
+
+    // Control points at the centroid and along the principal directions of the world points
+    let centroid = world.iter().sum::<DVec3>() / n;
+    let covariance: Vec<Vec<f64>> = (0..3)
+        .map(|i| {
+            (0..3)
+                .map(|j| {
+                    world
+                        .iter()
+                        .map(|p| (*p - centroid)[i] * (*p - centroid)[j])
+                        .sum()
+                })
+                .collect()
+        })
+        .collect();
+    let eigen = linalg::symmetric_eigen(&covariance);
+    if eigen[2].0 <= 0.0 {
+        return Err(PoseError::Degenerate);
+    }
+    if eigen[0].0 <= 1e-8 * eigen[2].0 {
+        return Err(PoseError::Coplanar);
+    }
+    let axes: Vec<DVec3> = eigen
+        .iter()
+        .map(|(value, vector)| DVec3::from_slice(vector) * (value / n).sqrt())
+        .collect();
+    let control = [
+        centroid,
+        centroid + axes[0],
+        centroid + axes[1],
+        centroid + axes[2],
+    ];
+
+    // Each point as a weighted sum of the control points, with weights summing to 1
+    let to_axes = DMat3::from_cols(axes[0], axes[1], axes[2]).inverse();
+    let alphas: Vec<[f64; 4]> = world
+        .iter()
+        .map(|p| {
+            let a = to_axes * (*p - centroid);
+            [1.0 - a.x - a.y - a.z, a.x, a.y, a.z]
+        })
+        .collect();
+
+    // Two equations per point in the 12 camera space coordinates of the control points
+    let mut rows = Vec::new();
+    for ((_, image), alpha) in points.iter().zip(&alphas) {
+        let (mut u, mut v) = (vec![0.0; 12], vec![0.0; 12]);
+        for (j, a) in alpha.iter().enumerate() {
+            u[3 * j] = *a;
+            u[3 * j + 2] = -a * image.x;
+            v[3 * j + 1] = *a;
+            v[3 * j + 2] = -a * image.y;
+        }
+        rows.push(u);
+        rows.push(v);
+    }
+    let eigen = linalg::symmetric_eigen(&linalg::normal_matrix(&rows));
+    let null: Vec<&[f64]> = eigen[..4].iter().map(|(_, v)| v.as_slice()).collect();
+    let control_of = |vector: &[f64], j: usize| DVec3::from_slice(&vector[3 * j..3 * j + 3]);
+
+    // The distance between each pair of control points, in terms of the products of the
+    // weights β of the null vectors:
+    // β11, β12, β22, β13, β23, β33, β14, β24, β34, β44
+    let pairs = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+    let (l, rho): (Vec<[f64; 10]>, Vec<f64>) = pairs
+        .iter()
+        .map(|&(a, b)| {
+            let d: Vec<DVec3> = null
+                .iter()
+                .map(|v| control_of(v, a) - control_of(v, b))
+                .collect();
+            let row = [
+                d[0].dot(d[0]),
+                2.0 * d[0].dot(d[1]),
+                d[1].dot(d[1]),
+                2.0 * d[0].dot(d[2]),
+                2.0 * d[1].dot(d[2]),
+                d[2].dot(d[2]),
+                2.0 * d[0].dot(d[3]),
+                2.0 * d[1].dot(d[3]),
+                2.0 * d[2].dot(d[3]),
+                d[3].dot(d[3]),
+            ];
+            (row, control[a].distance_squared(control[b]))
+        })
+        .unzip();
+
+    let pose_for = |betas: [f64; 4]| {
+        let mut camera_control = [DVec3::ZERO; 4];
+        for (j, c) in camera_control.iter_mut().enumerate() {
+            *c = null
+                .iter()
+                .zip(betas)
+                .map(|(v, beta)| control_of(v, j) * beta)
+                .sum();
+        }
+        let mut camera: Vec<DVec3> = alphas
+            .iter()
+            .map(|alpha| alpha.iter().zip(camera_control).map(|(a, c)| c * *a).sum())
+            .collect();
+
+        // The null vectors have no sign, so make sure the points end up in front of the camera
+        if camera.iter().map(|c| c.z).sum::<f64>() < 0.0 {
+            camera.iter_mut().for_each(|c| *c = -*c);
+        }
+        absolute_orientation(&world, &camera)
+    };
+
+    // Start from several approximations, keep the best
+    initial_betas(&l, &rho)
+        .into_iter()
+        .map(|betas| refine_betas(&l, &rho, betas))
+        .filter_map(pose_for)
+        .map(|pose| (reprojection_error(&points, pose), pose))
+        .filter(|(error, _)| error.is_finite())
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, (r, t))| Pose {
+            rotation: r.as_mat3(),
+            translation: t.as_vec3(),
+        })
+        .ok_or(PoseError::Degenerate)
+}
+
+/// The correspondences in double precision, if there are enough of them
+fn check_points(points: &[(Vec3, Vec2)]) -> Result<Vec<(DVec3, DVec2)>, PoseError> {
+    if points.len() < 4 {
+        return Err(PoseError::TooFewPoints(points.len()));
+    }
+    if points
+        .iter()
+        .any(|(world, image)| !world.is_finite() || !image.is_finite())
+    {
+        return Err(PoseError::NonFinite);
+    }
+
+    Ok(points
+        .iter()
+        .map(|(world, image)| (world.as_dvec3(), image.as_dvec2()))
+        .collect())
+}
+
+/// Starting points for the weights of the null vectors: each null vector on its own, and the
+/// linear approximations using 1, 2 or 3 of them. With few points the distance equations have
+/// local minima, so the more the better.
+fn initial_betas(l: &[[f64; 10]], rho: &[f64]) -> Vec<[f64; 4]> {
+    // Where β11, β22, β33 and β44 are in the distance equations
+    let squares = [0, 2, 5, 9];
+    let single = squares.iter().enumerate().filter_map(|(k, &column)| {
+        // The scale which best matches the distances, like for N = 1 in the paper
+        let camera: f64 = l.iter().map(|row| row[column]).sum();
+        let world: f64 = l
+            .iter()
+            .zip(rho)
+            .map(|(row, rho)| (row[column] * rho).sqrt())
+            .sum();
+        (camera > f64::EPSILON).then(|| {
+            let mut betas = [0.0; 4];
+            betas[k] = world / camera;
+            betas
+        })
+    });
+
+    single
+        .chain((1..=3).filter_map(|dimensions| approximate_betas(l, rho, dimensions)))
+        .collect()
+}
+
+/// Initial weights of the null vectors, solving the distance equations linearly by ignoring the
+/// products with the remaining null vectors
+fn approximate_betas(l: &[[f64; 10]], rho: &[f64], dimensions: usize) -> Option<[f64; 4]> {
+    let columns: &[usize] = match dimensions {
+        // β11, β12, β13, β14
+        1 => &[0, 1, 3, 6],
+        // β11, β12, β22
+        2 => &[0, 1, 2],
+        // β11, β12, β22, β13, β23
+        _ => &[0, 1, 2, 3, 4],
+    };
+    let rows: Vec<Vec<f64>> = l
+        .iter()
+        .map(|row| columns.iter().map(|&c| row[c]).collect())
+        .collect();
+    let b = linalg::least_squares(&rows, rho)?;
+
+    // β11 can come out negative, as the overall sign is arbitrary
+    let sign = if b[0] < 0.0 { -1.0 } else { 1.0 };
+    let beta1 = (sign * b[0]).sqrt();
+    if beta1 < f64::EPSILON {
+        return None;
+    }
+
+    Some(match dimensions {
+        1 => [
+            beta1,
+            sign * b[1] / beta1,
+            sign * b[2] / beta1,
+            sign * b[3] / beta1,
+        ],
+        _ => {
+            let beta2 = (sign * b[2]).max(0.0).sqrt();
+            let beta1 = if b[1] < 0.0 { -beta1 } else { beta1 };
+            let beta3 = if dimensions == 3 { b[3] / beta1 } else { 0.0 };
+            [beta1, beta2, beta3, 0.0]
+        }
+    })
+}
+
+/// Gauss-Newton on the distance equations, in the weights of all four null vectors
+fn refine_betas(l: &[[f64; 10]], rho: &[f64], mut betas: [f64; 4]) -> [f64; 4] {
+    const ITERATIONS: usize = 10;
+
+    for _ in 0..ITERATIONS {
+        let [b1, b2, b3, b4] = betas;
+        let products = [
+            b1 * b1,
+            b1 * b2,
+            b2 * b2,
+            b1 * b3,
+            b2 * b3,
+            b3 * b3,
+            b1 * b4,
+            b2 * b4,
+            b3 * b4,
+            b4 * b4,
+        ];
+
+        let (rows, residuals): (Vec<Vec<f64>>, Vec<f64>) = l
+            .iter()
+            .zip(rho)
+            .map(|(l, rho)| {
+                let jacobian = vec![
+                    2.0 * l[0] * b1 + l[1] * b2 + l[3] * b3 + l[6] * b4,
+                    l[1] * b1 + 2.0 * l[2] * b2 + l[4] * b3 + l[7] * b4,
+                    l[3] * b1 + l[4] * b2 + 2.0 * l[5] * b3 + l[8] * b4,
+                    l[6] * b1 + l[7] * b2 + l[8] * b3 + 2.0 * l[9] * b4,
+                ];
+                let distance: f64 = l.iter().zip(products).map(|(l, p)| l * p).sum();
+                (jacobian, rho - distance)
+            })
+            .unzip();
+
+        let Some(step) = linalg::least_squares(&rows, &residuals) else {
+            break;
+        };
+        betas
+            .iter_mut()
+            .zip(step)
+            .for_each(|(beta, step)| *beta += step);
+    }
+
+    betas
+}
+
+/// Gauss-Newton on the reprojection error, starting from the given pose. Steps which do not
+/// lower the error end the refinement.
+pub fn refine_pose(points: &[(Vec3, Vec2)], pose: Pose) -> Result<Pose, PoseError> {
+    const ITERATIONS: usize = 20;
+
+    let points = check_points(points)?;
+    let mut pose: DPose = (pose.rotation.as_dmat3(), pose.translation.as_dvec3());
+    let mut error = reprojection_error(&points, pose);
+
+    for _ in 0..ITERATIONS {
+        let (r, t) = pose;
+        let mut rows = Vec::new();
+        let mut residuals = Vec::new();
+
+        for (world, image) in &points {
+            // Points behind the camera have no projection to linearise, so leave them out of
+            // this step
+            let c = r * *world + t;
+            if c.z <= f64::EPSILON {
+                continue;
+            }
+
+            // Perturbing the camera space point by a small rotation ω and translation τ moves
+            // it by ω × c + τ
+            let derivatives = [
+                (
+                    DVec3::new(1.0 / c.z, 0.0, -c.x / (c.z * c.z)),
+                    image.x - c.x / c.z,
+                ),
+                (
+                    DVec3::new(0.0, 1.0 / c.z, -c.y / (c.z * c.z)),
+                    image.y - c.y / c.z,
+                ),
+            ];
+            for (gradient, residual) in derivatives {
+                let rotation = [DVec3::X, DVec3::Y, DVec3::Z].map(|e| gradient.dot(e.cross(c)));
+                rows.push(rotation.into_iter().chain(gradient.to_array()).collect());
+                residuals.push(residual);
+            }
+        }
+
+        let Some(step) = linalg::least_squares(&rows, &residuals) else {
+            break;
+        };
+        let omega = DMat3::from_quat(DQuat::from_scaled_axis(DVec3::from_slice(&step[..3])));
+        let candidate = (omega * r, omega * t + DVec3::from_slice(&step[3..]));
+
+        let candidate_error = reprojection_error(&points, candidate);
+        if candidate_error.total_cmp(&error).is_ge() {
+            break;
+        }
+        (pose, error) = (candidate, candidate_error);
+    }
+
+    Ok(Pose {
+        rotation: pose.0.as_mat3(),
+        translation: pose.1.as_vec3(),
+    })
+}
+
+/// The pose from world points and their normalized image coordinates
+pub fn estimate_pose(points: &[(Vec3, Vec2)], method: PnpMethod) -> Result<Pose, PoseError> {
+    let pose = epnp(points)?;
+
+    match method {
+        PnpMethod::Epnp => Ok(pose),
+        PnpMethod::Iterative => refine_pose(points, pose),
+    }
+}
+
+#[derive(Debug, Resource)]
+struct PoseEstimation {
+    method: PnpMethod,
+
+    /// Added to the projections of the world points
+    noise: SensorNoise,
+
+    show_estimate: bool,
+}
+
+impl Default for PoseEstimation {
+    fn default() -> Self {
+        Self {
+            method: PnpMethod::default(),
+            noise: SensorNoise::default(),
+            show_estimate: true,
+        }
+    }
+}
+
+/// The pose of the main camera as estimated, along with what it was estimated from
+#[derive(Debug, Resource)]
+struct EstimatedPose {
+    pose: Result<Pose, PoseError>,
+    /// Sensor positions with the noise added, by the index of their world point
+    observed: Vec<(usize, Vec2)>,
+    /// The actual pose of the main camera
+    truth: Option<Pose>,
+    /// Root mean square distance between the observed and reprojected points, in pixels
+    rms_error: f32,
+}
+
+impl Default for EstimatedPose {
+    fn default() -> Self {
+        Self {
+            pose: Err(PoseError::TooFewPoints(0)),
+            observed: Vec::new(),
+            truth: None,
+            rms_error: 0.0,
+        }
+    }
+}
+
+/// Streams of [`SeededRng`] used for the noise on the projections, one per
+/// [`SensorNoise::draw`]
+const POSE_NOISE_STREAM: u64 = 0x905E_0000;
+
+/// Angle of the rotation between two rotation matrices, in degrees
+fn rotation_difference(a: Mat3, b: Mat3) -> f32 {
+    Quat::from_mat3(&(a * b.transpose()))
+        .angle_between(Quat::IDENTITY)
+        .to_degrees()
+}
+
+fn ui_pose_estimation(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<PoseEstimation>,
+    estimated: Res<EstimatedPose>,
+) {
+    egui::Window::new("Pose estimation")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("The main camera pose from the world points and their projections");
+            ui.label("Needs at least 4 world points, which are not all on one plane");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut settings.method, PnpMethod::Epnp, "EPnP");
+                ui.radio_value(&mut settings.method, PnpMethod::Iterative, "Iterative");
+            });
+            settings.noise.ui(ui);
+            ui.checkbox(&mut settings.show_estimate, "Show estimated frustum");
+            ui.label(format!("{} correspondences", estimated.observed.len()));
+            ui.separator();
+
+            // Without a pose to compare to nothing was estimated, so the error means nothing
+            let (pose, truth) = match (&estimated.pose, estimated.truth) {
+                (_, None) => {
+                    ui.label("Needs the main camera under perspective");
+                    return;
+                }
+                (Ok(pose), Some(truth)) => (pose, truth),
+                (Err(e), Some(_)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, e.to_string());
+                    return;
+                }
+            };
+
+            let (x, y, z) = Quat::from_mat3(&pose.rotation).to_euler(EulerRot::XYZ);
+            let t = pose.translation;
+            ui.label("R");
+            matrix_label(ui, pose.rotation);
+            ui.label(format!(
+                "Rotation: ({:.2}°, {:.2}°, {:.2}°)",
+                x.to_degrees(),
+                y.to_degrees(),
+                z.to_degrees()
+            ));
+            ui.label(format!("t: ({:.3}, {:.3}, {:.3})", t.x, t.y, t.z));
+            ui.separator();
+
+            ui.label(format!(
+                "Rotation error: {:.3}°",
+                rotation_difference(pose.rotation, truth.rotation)
+            ));
+            ui.label(format!(
+                "Optical centre error: {:.4}",
+                pose.camera_to_world()
+                    .translation
+                    .distance(truth.camera_to_world().translation)
+            ));
+            ui.label(format!(
+                "RMS reprojection error: {:.2} px",
+                estimated.rms_error
+            ));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn estimate_camera_pose(
+    mut estimated: ResMut<EstimatedPose>,
+    settings: Res<PoseEstimation>,
+    intrinsics: Res<CameraIntrinsics>,
+    projection: Res<CameraProjection>,
+    size: Res<ImageSize>,
+    resolution: Res<ImageResolution>,
+    rng: Res<SeededRng>,
+    parents: ParentTransforms,
+    points: Query<(&GlobalTransform, &WorldPoint)>,
+) {
+    *estimated = EstimatedPose::default();
+
+    // Normalized image coordinates are only the direction of the point under perspective
+    if projection.model != ProjectionModel::Perspective {
+        return;
+    }
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+    let world_to_camera = rig.affine().inverse();
+    let truth = Pose {
+        rotation: world_to_camera.matrix3.into(),
+        translation: world_to_camera.translation.into(),
+    };
+
+    let mut noise = settings
+        .noise
+        .sampler(&rng, POSE_NOISE_STREAM, &size, &resolution);
+
+    // Sorted, such that the noise each point gets does not depend on the query order
+    let mut points: Vec<(usize, Vec3)> = points
+        .iter()
+        .map(|(transform, WorldPoint { index })| (*index, transform.translation()))
+        .collect();
+    points.sort_by_key(|(index, _)| *index);
+
+    let mut correspondences = Vec::new();
+    for (index, world) in points {
+        let Some(sensor) = intrinsics.project_world(rig, world) else {
+            continue;
+        };
+        let observed = sensor + noise();
+        estimated.observed.push((index, observed));
+        correspondences.push((world, intrinsics.normalize(observed)));
+    }
+
+    let pose = estimate_pose(&correspondences, settings.method);
+    if let Ok(pose) = &pose {
+        let to_pixel = |metric: Vec2| coords::metric_to_pixel(metric, **size, **resolution);
+        let world_to_estimate = pose.camera_to_world().inverse();
+        let squared: f32 = correspondences
+            .iter()
+            .zip(&estimated.observed)
+            .map(|((world, _), (_, observed))| {
+                intrinsics
+                    .project(world_to_estimate.transform_point3(*world))
+                    .map_or(f32::INFINITY, |sensor| {
+                        to_pixel(sensor).distance_squared(to_pixel(*observed))
+                    })
+            })
+            .sum();
+        estimated.rms_error = (squared / correspondences.len() as f32).sqrt();
+    }
+
+    estimated.pose = pose;
+    estimated.truth = Some(truth);
+}
+
+/// Lines from the optical centre through the corners of the sensor, on the given plane
+fn gizmo_frustum(
+    gizmos: &mut Gizmos,
+    camera_to_world: Affine3A,
+    corners: [Vec3; 4],
+    color: impl Into<Color> + Copy,
+) {
+    let corners = corners.map(|corner| camera_to_world.transform_point3(corner));
+    let centre = camera_to_world.translation.into();
+
+    for corner in corners {
+        gizmos.line(centre, corner, color);
+    }
+    gizmos.linestrip(corners.into_iter().chain([corners[0]]), color);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn gizmo_pose_estimation(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    settings: Res<PoseEstimation>,
+    estimated: Res<EstimatedPose>,
+    intrinsics: Res<CameraIntrinsics>,
+    size: Res<ImageSize>,
+    planes: Res<ImagePlanes>,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    points: Query<(&GlobalTransform, &WorldPoint)>,
+) {
+    let (true, Ok(pose), Some(rig)) = (settings.show_estimate, &estimated.pose, parents.rig())
+    else {
+        return;
+    };
+    let depth = planes.main_depth();
+    let corners = sensor_corners(&size).map(|corner| intrinsics.back_project(corner, depth));
+    let camera_to_world = pose.camera_to_world();
+    let color = palettes::tailwind::ORANGE_400;
+
+    gizmo_frustum(
+        &mut gizmos,
+        rig.affine(),
+        corners,
+        palettes::tailwind::GREEN_400,
+    );
+    gizmo_frustum(&mut gizmos, camera_to_world, corners, color);
+
+    // The observed points on the estimated image plane, and their rays to the world points
+    for (index, observed) in &estimated.observed {
+        gizmos.sphere(
+            camera_to_world.transform_point3(intrinsics.back_project(*observed, depth)),
+            Quat::default(),
+            point_settings.point_size / 2.,
+            cache.color(*index),
+        );
+    }
+    for (transform, WorldPoint { index }) in &points {
+        gizmos.line(
+            camera_to_world.translation.into(),
+            transform.translation(),
+            cache.color(*index).with_alpha(0.3),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn known_pose() -> Pose {
+        Pose {
+            rotation: Mat3::from_euler(EulerRot::XYZ, 0.1, -0.3, 0.2),
+            translation: Vec3::new(0.2, -0.1, 4.0),
+        }
+    }
+
+    /// Non-coplanar world points, with where the known pose sees them
+    fn correspondences() -> Vec<(Vec3, Vec2)> {
+        let pose = known_pose();
+        [
+            Vec3::new(-1.0, -0.5, 0.3),
+            Vec3::new(0.8, -0.7, -0.4),
+            Vec3::new(0.4, 0.9, 0.6),
+            Vec3::new(-0.6, 0.5, -0.8),
+            Vec3::new(0.1, 0.2, 1.0),
+            Vec3::new(-0.2, -1.0, -0.1),
+        ]
+        .into_iter()
+        .map(|world| {
+            let camera = pose.rotation * world + pose.translation;
+            (world, camera.xy() / camera.z)
+        })
+        .collect()
+    }
+
+    fn assert_pose_eq(estimate: Pose, expected: Pose) {
+        assert!(
+            estimate.rotation.abs_diff_eq(expected.rotation, 1e-4),
+            "{} != {}",
+            estimate.rotation,
+            expected.rotation
+        );
+        assert!(
+            estimate.translation.abs_diff_eq(expected.translation, 1e-4),
+            "{} != {}",
+            estimate.translation,
+            expected.translation
+        );
+    }
+
+    #[test]
+    fn epnp_recovers_known_pose() {
+        let points = correspondences();
+
+        // With only 4 points EPnP is close but not exact, which refinement is for
+        for n in [5, 6] {
+            assert_pose_eq(epnp(&points[..n]).unwrap(), known_pose());
+        }
+    }
+
+    #[test]
+    fn refinement_recovers_known_pose() {
+        let points = correspondences();
+        let start = Pose {
+            rotation: known_pose().rotation * Mat3::from_rotation_z(0.05),
+            translation: known_pose().translation + Vec3::new(0.05, 0.0, -0.1),
+        };
+        assert_pose_eq(refine_pose(&points, start).unwrap(), known_pose());
+
+        for n in [4, 5, 6] {
+            assert_pose_eq(
+                estimate_pose(&points[..n], PnpMethod::Iterative).unwrap(),
+                known_pose(),
+            );
+        }
+    }
+
+    #[test]
+    fn degenerate_points_are_errors() {
+        let points = correspondences();
+        assert_eq!(epnp(&points[..3]), Err(PoseError::TooFewPoints(3)));
+
+        let coplanar: Vec<(Vec3, Vec2)> = points
+            .iter()
+            .map(|(world, image)| (world.with_z(0.0), *image))
+            .collect();
+        assert_eq!(epnp(&coplanar), Err(PoseError::Coplanar));
+    }
+}
@@ -0,0 +1,308 @@
+use bevy::{
+    math::{DMat3, DVec3},
+    prelude::*,
+};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use thiserror::Error;
+
+use crate::camera::{CameraExtrinsics, CameraIntrinsics};
+
+/// A panel showing (and editing) the full camera matrix P = K[R|t]
+pub struct ProjectionMatrixPlugin;
+
+impl Plugin for ProjectionMatrixPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProjectionMatrixEditor>()
+            .add_systems(Update, ui_projection_matrix);
+    }
+}
+
+/// Row major 3x4 camera matrix
+pub type ProjectionMatrix = [[f32; 4]; 3];
+
+#[derive(Debug, Error, PartialEq)]
+pub enum DecompositionError {
+    #[error("the matrix contains non-finite values")]
+    NonFinite,
+
+    #[error("the left 3x3 block is singular, so this is not a finite camera")]
+    Singular,
+}
+
+/// The parts of a finite projective camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    pub k: Mat3,
+    pub r: Mat3,
+    pub t: Vec3,
+}
+
+/// P = K[R|t]
+pub fn compose(intrinsics: &CameraIntrinsics, extrinsics: &CameraExtrinsics) -> ProjectionMatrix {
+    let k = intrinsics.matrix();
+    let kr = k * extrinsics.rotation_matrix();
+    let kt = k * extrinsics.translation;
+
+    [0, 1, 2].map(|row| {
+        let r = kr.row(row);
+        [r.x, r.y, r.z, kt[row]]
+    })
+}
+
+/// Split P into K, R and t, using an RQ decomposition of the left 3x3 block.
+///
+/// P is only defined up to scale, so K is normalized such that its bottom right entry is 1,
+/// and the diagonal of K is made positive.
+pub fn decompose(p: &ProjectionMatrix) -> Result<Decomposition, DecompositionError> {
+    if p.iter().flatten().any(|v| !v.is_finite()) {
+        return Err(DecompositionError::NonFinite);
+    }
+
+    let row = |r: usize| DVec3::new(p[r][0] as f64, p[r][1] as f64, p[r][2] as f64);
+    let mut m = DMat3::from_cols(row(0), row(1), row(2)).transpose();
+    let mut p4 = DVec3::new(p[0][3] as f64, p[1][3] as f64, p[2][3] as f64);
+
+    // Relative to the magnitude of the entries, since P is only defined up to scale
+    let det = m.determinant();
+    let largest = m
+        .to_cols_array()
+        .iter()
+        .fold(0.0, |acc: f64, v| acc.max(v.abs()));
+    if largest == 0.0 || det.abs() < 1e-9 * largest.powi(3) {
+        return Err(DecompositionError::Singular);
+    }
+
+    // Fix the overall sign of P such that R is a proper rotation
+    if det < 0.0 {
+        m = -m;
+        p4 = -p4;
+    }
+
+    let (k, r) = rq(m);
+
+    // The scale of P ends up in K, so divide it out
+    let scale = k.z_axis.z;
+    let t = k.inverse() * p4;
+    let k = k / scale;
+
+    Ok(Decomposition {
+        k: k.as_mat3(),
+        r: r.as_mat3(),
+        t: t.as_vec3(),
+    })
+}
+
+/// M = K R with K upper triangular with a positive diagonal, and R orthogonal.
+/// Uses Givens rotations, see Hartley & Zisserman (A4.1.1).
+fn rq(m: DMat3) -> (DMat3, DMat3) {
+    // Entry (row, col), the matrices are column major
+    fn at(m: &DMat3, row: usize, col: usize) -> f64 {
+        m.col(col)[row]
+    }
+    fn givens(a: f64, b: f64) -> (f64, f64) {
+        let norm = a.hypot(b);
+        if norm < f64::EPSILON {
+            (1.0, 0.0)
+        } else {
+            (a / norm, b / norm)
+        }
+    }
+
+    // Zero (2, 1)
+    let (c, s) = givens(-at(&m, 2, 2), at(&m, 2, 1));
+    let qx = DMat3::from_cols(
+        DVec3::new(1.0, 0.0, 0.0),
+        DVec3::new(0.0, c, s),
+        DVec3::new(0.0, -s, c),
+    );
+    let m = m * qx;
+
+    // Zero (2, 0)
+    let (c, s) = givens(at(&m, 2, 2), at(&m, 2, 0));
+    let qy = DMat3::from_cols(
+        DVec3::new(c, 0.0, -s),
+        DVec3::new(0.0, 1.0, 0.0),
+        DVec3::new(s, 0.0, c),
+    );
+    let m = m * qy;
+
+    // Zero (1, 0)
+    let (c, s) = givens(-at(&m, 1, 1), at(&m, 1, 0));
+    let qz = DMat3::from_cols(
+        DVec3::new(c, s, 0.0),
+        DVec3::new(-s, c, 0.0),
+        DVec3::new(0.0, 0.0, 1.0),
+    );
+    let k = m * qz;
+    let r = (qx * qy * qz).transpose();
+
+    // Flip signs such that the diagonal of K is positive, D is its own inverse
+    let d = DMat3::from_diagonal(DVec3::new(
+        at(&k, 0, 0).signum(),
+        at(&k, 1, 1).signum(),
+        at(&k, 2, 2).signum(),
+    ));
+
+    (k * d, d * r)
+}
+
+#[derive(Debug, Default, Resource)]
+struct ProjectionMatrixEditor {
+    /// Edits not yet applied to the scene, if any
+    pending: Option<ProjectionMatrix>,
+}
+
+fn ui_projection_matrix(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<ProjectionMatrixEditor>,
+    mut intrinsics: ResMut<CameraIntrinsics>,
+    mut extrinsics: ResMut<CameraExtrinsics>,
+) {
+    let current = compose(&intrinsics, &extrinsics);
+    let mut p = editor.pending.unwrap_or(current);
+    let mut edited = false;
+    let mut apply = false;
+    let mut reset = false;
+
+    egui::Window::new("Projection matrix")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label("P = K[R|t]");
+            egui::Grid::new("projection matrix").show(ui, |ui| {
+                for row in &mut p {
+                    for value in row {
+                        edited |= ui
+                            .add(egui::DragValue::new(value).speed(0.01).fixed_decimals(3))
+                            .changed();
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+
+            match decompose(&p) {
+                Ok(Decomposition { k, r, t }) => {
+                    ui.label("K");
+                    matrix_label(ui, k);
+                    ui.label("R");
+                    matrix_label(ui, r);
+                    ui.label(format!("t: ({:.3}, {:.3}, {:.3})", t.x, t.y, t.z));
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, e.to_string());
+                }
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                apply = ui.button("Apply").clicked();
+                reset = ui.button("Reset").clicked();
+            });
+        });
+
+    if edited {
+        editor.pending = Some(p);
+    }
+    if reset {
+        editor.pending = None;
+    }
+    if apply {
+        // Errors are shown in the panel already, so just keep the edits around
+        if let Ok(Decomposition { k, r, t }) = decompose(&p) {
+            let k = k.transpose();
+            *intrinsics = CameraIntrinsics {
+                fx: k.x_axis.x,
+                skew: k.x_axis.y,
+                cx: k.x_axis.z,
+                fy: k.y_axis.y,
+                cy: k.y_axis.z,
+            };
+
+            let (x, y, z) = Quat::from_mat3(&r).to_euler(EulerRot::XYZ);
+            *extrinsics = CameraExtrinsics {
+                rotation: Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees()),
+                translation: t,
+            };
+
+            editor.pending = None;
+        }
+    }
+}
+
+pub fn matrix_label(ui: &mut egui::Ui, m: Mat3) {
+    for row in 0..3 {
+        let r = m.row(row);
+        ui.monospace(format!("{:8.3} {:8.3} {:8.3}", r.x, r.y, r.z));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cameras() -> [(CameraIntrinsics, CameraExtrinsics); 3] {
+        [
+            (CameraIntrinsics::default(), CameraExtrinsics::default()),
+            (
+                CameraIntrinsics {
+                    fx: 1.4,
+                    fy: 1.1,
+                    cx: 0.2,
+                    cy: -0.1,
+                    skew: 0.05,
+                },
+                CameraExtrinsics {
+                    rotation: Vec3::new(10.0, -25.0, 40.0),
+                    translation: Vec3::new(0.3, -0.2, 5.0),
+                },
+            ),
+            (
+                CameraIntrinsics {
+                    fx: 0.8,
+                    fy: 0.8,
+                    cx: 0.0,
+                    cy: 0.3,
+                    skew: 0.0,
+                },
+                CameraExtrinsics {
+                    rotation: Vec3::new(170.0, 80.0, -120.0),
+                    translation: Vec3::new(-1.0, 2.0, -3.0),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn decompose_inverts_compose() {
+        for (intrinsics, extrinsics) in cameras() {
+            let p = compose(&intrinsics, &extrinsics);
+
+            // P is only defined up to scale, including negative ones
+            for scale in [1.0, 2.5, -0.7] {
+                let scaled = p.map(|row| row.map(|v| v * scale));
+                let Decomposition { k, r, t } = decompose(&scaled).unwrap();
+
+                assert!(k.abs_diff_eq(intrinsics.matrix(), 1e-4), "{k} at {scale}");
+                assert!(
+                    r.abs_diff_eq(extrinsics.rotation_matrix(), 1e-4),
+                    "{r} at {scale}"
+                );
+                assert!(
+                    t.abs_diff_eq(extrinsics.translation, 1e-4),
+                    "{t} at {scale}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn decompose_rejects_singular_and_non_finite() {
+        let mut p = compose(&CameraIntrinsics::default(), &CameraExtrinsics::default());
+        p[2] = p[0];
+        assert_eq!(decompose(&p), Err(DecompositionError::Singular));
+        assert_eq!(decompose(&[[0.0; 4]; 3]), Err(DecompositionError::Singular));
+
+        p[1][3] = f32::NAN;
+        assert_eq!(decompose(&p), Err(DecompositionError::NonFinite));
+    }
+}
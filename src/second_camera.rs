@@ -0,0 +1,278 @@
+use bevy::{color::palettes, ecs::system::SystemParam, prelude::*};
+use bevy_inspector_egui::{inspector_options::ReflectInspectorOptions, InspectorOptions};
+use bevy_mod_picking::prelude::Pickable;
+
+use crate::{
+    camera::CameraIntrinsics, image_plane_mesh, material_mesh_cache::MeshMaterialCache,
+    sensor_corners, setup_parent_spatial, should_remake, world_points::WorldPoint, ImagePlanes,
+    ImagePoints, ImageSize, MainPointsParent, ParentTransforms,
+};
+
+/// A second pinhole camera next to the main one, imaging the same world points.
+/// It shares the intrinsics and plane depths of the main camera, only its pose differs.
+pub struct SecondCameraPlugin;
+
+impl Plugin for SecondCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SecondCamera>()
+            .register_type::<SecondCamera>()
+            .add_systems(Startup, setup_second_rig.after(setup_parent_spatial))
+            .add_systems(
+                Update,
+                (
+                    sync_second_rig.run_if(resource_changed::<SecondCamera>.or_else(should_remake)),
+                    gizmo_second_view,
+                ),
+            );
+    }
+}
+
+/// The pose of the second camera relative to the main one, as [R|t] mapping the main camera's
+/// space into the second camera's space:
+///
+/// ```text
+/// x₂ = R * x₁ + t
+/// ```
+///
+/// Like [`crate::camera::CameraExtrinsics`], with the main camera in place of the world.
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct SecondCamera {
+    pub enabled: bool,
+
+    /// Where the second optical centre is in the main camera's space, i.e. -Rᵀt
+    pub baseline: Vec3,
+
+    /// Euler angles (XYZ order, in degrees) of R
+    pub rotation: Vec3,
+
+    /// How many of the main camera's image planes the second camera has too
+    #[inspector(min = 1)]
+    pub num_planes: usize,
+
+    /// From the second optical centre to each world point
+    pub show_rays: bool,
+}
+
+impl Default for SecondCamera {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            baseline: Vec3::new(0.5, 0.0, 0.0),
+            rotation: Vec3::ZERO,
+            num_planes: 1,
+            show_rays: true,
+        }
+    }
+}
+
+impl SecondCamera {
+    pub fn rotation_quat(&self) -> Quat {
+        let r = self.rotation;
+        Quat::from_euler(
+            EulerRot::XYZ,
+            r.x.to_radians(),
+            r.y.to_radians(),
+            r.z.to_radians(),
+        )
+    }
+
+    /// R
+    pub fn rotation_matrix(&self) -> Mat3 {
+        Mat3::from_quat(self.rotation_quat())
+    }
+
+    /// t
+    pub fn translation(&self) -> Vec3 {
+        -(self.rotation_quat() * self.baseline)
+    }
+
+    /// Where the second rig sits on the main one
+    pub fn second_to_main(&self) -> Transform {
+        Transform::from_translation(self.baseline).with_rotation(self.rotation_quat().inverse())
+    }
+}
+
+/// The second camera rig: Its optical centre, with its image planes as children.
+/// A child of [`MainPointsParent`], so it moves along with the main camera.
+#[derive(Debug, Resource, Deref)]
+pub struct SecondRigParent {
+    entity: Entity,
+}
+
+/// An image plane of the second camera
+#[derive(Debug, Component)]
+pub struct SecondImagePlane {
+    /// Into [`ImagePlanes::depths`]
+    pub index: usize,
+}
+
+fn setup_second_rig(mut commands: Commands, main_points_parent: Res<MainPointsParent>) {
+    let mut id = Entity::PLACEHOLDER;
+    commands.entity(**main_points_parent).with_children(|b| {
+        id = b
+            .spawn((
+                SpatialBundle::INHERITED_IDENTITY,
+                Name::new("second camera rig"),
+            ))
+            .id();
+    });
+
+    commands.insert_resource(SecondRigParent { entity: id });
+}
+
+/// Where the second camera currently is, and what it sees
+#[derive(SystemParam)]
+pub struct SecondRig<'w, 's> {
+    pub settings: Res<'w, SecondCamera>,
+    parent: Res<'w, SecondRigParent>,
+    transforms: Query<'w, 's, &'static GlobalTransform>,
+}
+
+impl SecondRig<'_, '_> {
+    /// Second camera space to world space, `None` while the second camera is disabled
+    pub fn transform(&self) -> Option<&GlobalTransform> {
+        if !self.settings.enabled {
+            return None;
+        }
+
+        self.transforms.get(**self.parent).ok()
+    }
+
+    /// The normalized image coordinates of a world space point in the second camera,
+    /// if it is in front of it
+    pub fn project(&self, world: Vec3) -> Option<Vec2> {
+        let point = self.transform()?.affine().inverse().transform_point3(world);
+
+        (point.z > f32::EPSILON).then(|| point.xy() / point.z)
+    }
+
+    /// A world space point as seen by the main camera (placed by `main_rig`) and this one, as
+    /// sensor positions. `None` if it is behind either, or the second camera is disabled.
+    pub fn observe(
+        &self,
+        intrinsics: &CameraIntrinsics,
+        main_rig: &GlobalTransform,
+        world: Vec3,
+    ) -> Option<(Vec2, Vec2)> {
+        Some((
+            intrinsics.project_world(main_rig, world)?,
+            intrinsics.project_world(self.transform()?, world)?,
+        ))
+    }
+}
+
+/// The same as [`crate::camera::CameraProjection::plane_transform`] under perspective
+fn pinhole_plane_transform(depth: f32) -> Transform {
+    Transform::from_xyz(0.0, 0.0, depth).with_scale(Vec3::new(depth, depth, 1.0))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn sync_second_rig(
+    mut commands: Commands,
+    mut cache: MeshMaterialCache,
+    settings: Res<SecondCamera>,
+    parent: Res<SecondRigParent>,
+    size: Res<ImageSize>,
+    planes: Res<ImagePlanes>,
+    intrinsics: Res<CameraIntrinsics>,
+    mut rig: Query<(&mut Transform, &mut Visibility), Without<SecondImagePlane>>,
+    mut existing: Query<(Entity, &SecondImagePlane, &mut Transform, &mut Handle<Mesh>)>,
+) {
+    let Ok((mut transform, mut visibility)) = rig.get_mut(**parent) else {
+        warn!("unexpected second camera rig missing");
+        return;
+    };
+    *transform = settings.second_to_main();
+    *visibility = if settings.enabled {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    let mesh = cache.add_mesh(image_plane_mesh(
+        sensor_corners(&size).map(|corner| intrinsics.normalize(corner)),
+    ));
+    let depths: Vec<f32> = planes
+        .depths()
+        .into_iter()
+        .take(settings.num_planes)
+        .collect();
+
+    for (entity, SecondImagePlane { index }, mut transform, mut plane_mesh) in &mut existing {
+        match depths.get(*index) {
+            Some(&depth) => {
+                *transform = pinhole_plane_transform(depth);
+                *plane_mesh = mesh.clone();
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+
+    let material = cache.material(palettes::tailwind::SKY_300.with_alpha(0.05).to_u8_array());
+    for (i, depth) in depths.into_iter().enumerate().skip(existing.iter().count()) {
+        commands.entity(**parent).with_children(|b| {
+            b.spawn((
+                MaterialMeshBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: pinhole_plane_transform(depth),
+                    ..default()
+                },
+                Pickable {
+                    should_block_lower: false,
+                    is_hoverable: true,
+                },
+                SecondImagePlane { index: i },
+                Name::new(format!("second plane-{}", i + 1)),
+            ));
+        });
+    }
+}
+
+/// The second optical centre, the baseline to the main one, and the world points as the second
+/// camera sees them
+fn gizmo_second_view(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    second: SecondRig,
+    planes: Res<ImagePlanes>,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    points: Query<(&GlobalTransform, &WorldPoint)>,
+) {
+    let (Some(rig), Some(main_rig)) = (second.transform(), parents.rig()) else {
+        return;
+    };
+    let centre = rig.translation();
+
+    gizmos.axes(*rig, 0.2);
+    gizmos.line(main_rig.translation(), centre, palettes::tailwind::SKY_400);
+
+    let depths: Vec<f32> = planes
+        .depths()
+        .into_iter()
+        .take(second.settings.num_planes)
+        .collect();
+
+    for (transform, WorldPoint { index }) in &points {
+        let color = cache.color(*index);
+        let world = transform.translation();
+
+        if second.settings.show_rays {
+            gizmos.line(centre, world, color);
+        }
+
+        let Some(normalized) = second.project(world) else {
+            continue;
+        };
+        for &depth in &depths {
+            gizmos.sphere(
+                rig.transform_point(normalized.extend(1.0) * depth),
+                Quat::default(),
+                point_settings.point_size / 2.,
+                color,
+            );
+        }
+    }
+}
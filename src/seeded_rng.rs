@@ -0,0 +1,49 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+pub struct SeededRngPlugin;
+
+impl Plugin for SeededRngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeededRng>()
+            .register_type::<SeededRng>();
+    }
+}
+
+/// Source of all randomness, such that the same seed gives the same result every time.
+///
+/// Rather than sharing one sequence of numbers (which would make results depend on who asks first),
+/// users get their own deterministic stream.
+#[derive(Debug, Default, Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SeededRng {
+    seed: u64,
+}
+
+impl SeededRng {
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// A fresh rng for the given stream, which starts over each time this is called.
+    /// ChaCha8 rather than `StdRng`, as that may change its algorithm between versions of `rand`.
+    pub fn stream(&self, stream: u64) -> ChaCha8Rng {
+        // Spread the stream over the bits of the seed, so nearby streams are not correlated
+        ChaCha8Rng::seed_from_u64(self.seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+}
+
+/// Standard normal, using the Box-Muller transform
+pub fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let v: f32 = rng.gen();
+
+    (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+}
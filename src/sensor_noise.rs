@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::egui;
+
+use crate::{
+    seeded_rng::{gaussian, SeededRng},
+    ImageResolution, ImageSize,
+};
+
+/// Gaussian noise on sensor positions, to see how an estimate holds up against measurement error
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SensorNoise {
+    /// Standard deviation, in pixels
+    pub pixel_sigma: f32,
+
+    /// Bumped to draw different noise
+    pub draw: u64,
+}
+
+impl SensorNoise {
+    /// σ, and a button for a new draw
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Pixel noise (σ)");
+            ui.add(
+                egui::DragValue::new(&mut self.pixel_sigma)
+                    .speed(0.1)
+                    .range(0.0..=50.0),
+            );
+            if ui.button("New noise").clicked() {
+                self.draw += 1;
+            }
+        });
+    }
+
+    /// Noise in sensor units, from the given stream of [`SeededRng`] offset by [`Self::draw`]
+    pub(crate) fn sampler(
+        &self,
+        rng: &SeededRng,
+        stream: u64,
+        size: &ImageSize,
+        resolution: &ImageResolution,
+    ) -> impl FnMut() -> Vec2 {
+        let mut rng = rng.stream(stream.wrapping_add(self.draw));
+        let scale = self.pixel_sigma * **size / resolution.as_vec2();
+
+        move || Vec2::new(gaussian(&mut rng), gaussian(&mut rng)) * scale
+    }
+}
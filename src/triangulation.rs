@@ -0,0 +1,487 @@
+use bevy::{color::palettes, math::Affine3A, prelude::*};
+use bevy_inspector_egui::{bevy_egui::EguiContexts, egui};
+use bevy_mod_picking::{
+    events::{Click, Pointer},
+    pointer::PointerButton,
+};
+
+use crate::{
+    camera::{CameraIntrinsics, CameraProjection, ProjectionModel},
+    epipolar::View,
+    linalg,
+    material_mesh_cache::MeshMaterialCache,
+    point_editing::{detect_main_plane_clicks, MainPlaneClick, PointerTravel},
+    second_camera::{SecondImagePlane, SecondRig},
+    seeded_rng::SeededRng,
+    sensor_noise::SensorNoise,
+    world_points::WorldPoint,
+    ImagePlanes, ImagePoints, ImageResolution, ImageSize, ParentTransforms,
+};
+
+/// Pick the same point in both cameras, and reconstruct it in 3D with the linear (DLT) and
+/// midpoint methods
+pub struct TriangulationPlugin;
+
+impl Plugin for TriangulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Triangulation>()
+            .init_resource::<CorrespondenceTool>()
+            .init_resource::<TriangulatedPoints>()
+            .add_systems(
+                Update,
+                (
+                    ui_triangulation,
+                    pick_correspondences,
+                    triangulate_correspondences,
+                    gizmo_triangulation,
+                )
+                    .chain()
+                    .after(detect_main_plane_clicks),
+            );
+    }
+}
+
+/// The same point as seen by both cameras, as sensor positions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correspondence {
+    pub main: Vec2,
+    pub second: Vec2,
+}
+
+impl Correspondence {
+    fn get(&self, view: View) -> Vec2 {
+        match view {
+            View::Main => self.main,
+            View::Second => self.second,
+        }
+    }
+}
+
+#[derive(Debug, Default, Resource)]
+struct Triangulation {
+    correspondences: Vec<Correspondence>,
+
+    /// Added to both sensor positions
+    noise: SensorNoise,
+}
+
+/// The halves of the correspondence being picked, if picking
+#[derive(Debug, Default, Resource)]
+pub struct CorrespondenceTool {
+    active: bool,
+    main: Option<Vec2>,
+    second: Option<Vec2>,
+}
+
+/// Whether clicks on the main image plane go to the correspondence being picked
+pub fn is_picking_correspondence(tool: Res<CorrespondenceTool>) -> bool {
+    tool.active
+}
+
+/// Linear triangulation: The X for which the projection PX of each view is parallel to the
+/// observed x, i.e. x × PX = 0, in the least squares sense. The last right singular vector of the
+/// stacked equations, see Hartley & Zisserman (12.2).
+///
+/// Each view is its camera to world transform, with the point in normalized image coordinates.
+/// `None` for points at infinity, e.g. when the rays are parallel.
+pub fn triangulate_dlt(views: &[(Affine3A, Vec2)]) -> Option<Vec3> {
+    let mut rows = Vec::new();
+
+    for (camera_to_world, normalized) in views {
+        // [R|t], from world into camera space
+        let world_to_camera = camera_to_world.inverse();
+        let p = |row: usize| {
+            let r = world_to_camera.matrix3.row(row);
+            [r.x, r.y, r.z, world_to_camera.translation[row]].map(f64::from)
+        };
+        let (p1, p2, p3) = (p(0), p(1), p(2));
+        let (x, y) = (normalized.x as f64, normalized.y as f64);
+
+        rows.push((0..4).map(|i| x * p3[i] - p1[i]).collect());
+        rows.push((0..4).map(|i| y * p3[i] - p2[i]).collect());
+    }
+
+    let (x, _) = linalg::null_vector(&rows)?;
+    if x[3].abs() < 1e-12 {
+        return None;
+    }
+
+    Some(Vec3::new(
+        (x[0] / x[3]) as f32,
+        (x[1] / x[3]) as f32,
+        (x[2] / x[3]) as f32,
+    ))
+}
+
+/// The ray from the optical centre through the given normalized image coordinates
+pub fn back_projected_ray(camera_to_world: Affine3A, normalized: Vec2) -> Ray3d {
+    Ray3d::new(
+        camera_to_world.translation.into(),
+        camera_to_world.transform_vector3(normalized.extend(1.0)),
+    )
+}
+
+/// Where the rays come closest to each other, on each ray. `None` for parallel rays.
+pub fn closest_points(a: Ray3d, b: Ray3d) -> Option<(Vec3, Vec3)> {
+    let (da, db) = (*a.direction, *b.direction);
+    let w = a.origin - b.origin;
+    let (ab, aw, bw) = (da.dot(db), da.dot(w), db.dot(w));
+
+    let denominator = 1.0 - ab * ab;
+    if denominator < 1e-9 {
+        return None;
+    }
+
+    Some((
+        a.get_point((ab * bw - aw) / denominator),
+        b.get_point((bw - ab * aw) / denominator),
+    ))
+}
+
+/// A [`Correspondence`] reconstructed from noisy sensor positions
+#[derive(Debug, Clone, Copy)]
+struct TriangulatedPoint {
+    /// Sensor positions with the noise added
+    observed: Correspondence,
+    rays: [Ray3d; 2],
+    dlt: Option<Vec3>,
+    /// The closest points on each ray, the estimate is halfway
+    closest: Option<(Vec3, Vec3)>,
+    /// The DLT estimate without noise
+    exact: Option<Vec3>,
+}
+
+impl TriangulatedPoint {
+    fn midpoint(&self) -> Option<Vec3> {
+        self.closest.map(|(a, b)| (a + b) / 2.)
+    }
+}
+
+#[derive(Debug, Default, Resource)]
+struct TriangulatedPoints(Vec<TriangulatedPoint>);
+
+/// Streams of [`SeededRng`] used for the noise on correspondences, one per
+/// [`SensorNoise::draw`]
+const TRIANGULATION_NOISE_STREAM: u64 = 0x7A1A_0000;
+
+#[allow(clippy::too_many_arguments)]
+fn ui_triangulation(
+    mut contexts: EguiContexts,
+    mut triangulation: ResMut<Triangulation>,
+    mut tool: ResMut<CorrespondenceTool>,
+    triangulated: Res<TriangulatedPoints>,
+    intrinsics: Res<CameraIntrinsics>,
+    parents: ParentTransforms,
+    second: SecondRig,
+    world_points: Query<&GlobalTransform, With<WorldPoint>>,
+) {
+    egui::Window::new("Triangulation")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let (Some(main_rig), Some(_)) = (parents.rig(), second.transform()) else {
+                ui.label("Enable the second camera to triangulate");
+                return;
+            };
+
+            ui.label("Click a point on the main image plane of each camera");
+            ui.horizontal(|ui| {
+                let label = if tool.active { "Stop picking" } else { "Pick" };
+                if ui.button(label).clicked() {
+                    *tool = CorrespondenceTool {
+                        active: !tool.active,
+                        ..default()
+                    };
+                }
+                if tool.active {
+                    let picked = |half: Option<Vec2>| if half.is_some() { "✔" } else { "…" };
+                    ui.label(format!(
+                        "main {}, second {}",
+                        picked(tool.main),
+                        picked(tool.second)
+                    ));
+                }
+            });
+
+            if ui.button("From world points").clicked() {
+                triangulation.correspondences = world_points
+                    .iter()
+                    .filter_map(|transform| {
+                        let (main, second) =
+                            second.observe(&intrinsics, main_rig, transform.translation())?;
+                        Some(Correspondence { main, second })
+                    })
+                    .collect();
+            }
+            ui.separator();
+
+            triangulation.noise.ui(ui);
+            ui.label(format!(
+                "Baseline: {:.3}",
+                second.settings.baseline.length()
+            ));
+            ui.label("Errors are relative to triangulating without noise");
+            ui.separator();
+
+            let format_point = |point: Option<Vec3>| match point {
+                Some(p) => format!("({:.3}, {:.3}, {:.3})", p.x, p.y, p.z),
+                None => "at infinity".to_string(),
+            };
+            let format_error = |estimate: Option<Vec3>, exact: Option<Vec3>| match (estimate, exact)
+            {
+                (Some(estimate), Some(exact)) => format!("{:.4}", estimate.distance(exact)),
+                _ => "-".to_string(),
+            };
+
+            let mut remove = None;
+            egui::Grid::new("triangulated points").show(ui, |ui| {
+                for header in ["", "DLT", "error", "midpoint", "error", "ray gap", ""] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+
+                for (index, point) in triangulated.0.iter().enumerate() {
+                    ui.label(index.to_string());
+                    ui.monospace(format_point(point.dlt));
+                    ui.monospace(format_error(point.dlt, point.exact));
+                    ui.monospace(format_point(point.midpoint()));
+                    ui.monospace(format_error(point.midpoint(), point.exact));
+                    ui.monospace(match point.closest {
+                        Some((a, b)) => format!("{:.4}", a.distance(b)),
+                        None => "-".to_string(),
+                    });
+                    if ui.button("Remove").clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if let Some(index) = remove {
+                triangulation.correspondences.remove(index);
+            }
+            if !triangulation.correspondences.is_empty() && ui.button("Remove all").clicked() {
+                triangulation.correspondences.clear();
+            }
+        });
+}
+
+fn pick_correspondences(
+    mut tool: ResMut<CorrespondenceTool>,
+    mut triangulation: ResMut<Triangulation>,
+    travel: Res<PointerTravel>,
+    intrinsics: Res<CameraIntrinsics>,
+    mut main_clicks: EventReader<MainPlaneClick>,
+    mut clicks: EventReader<Pointer<Click>>,
+    second_planes: Query<(&SecondImagePlane, &GlobalTransform)>,
+) {
+    if !tool.active {
+        main_clicks.clear();
+        clicks.clear();
+        return;
+    }
+
+    for MainPlaneClick { normalized } in main_clicks.read() {
+        tool.main = Some(intrinsics.denormalize(*normalized));
+    }
+
+    for click in clicks.read() {
+        if click.event.button != PointerButton::Primary
+            || !travel.is_click(click.pointer_id, click.event.button)
+        {
+            continue;
+        }
+        let (Ok((SecondImagePlane { index: 0 }, plane)), Some(position)) =
+            (second_planes.get(click.target), click.hit.position)
+        else {
+            continue;
+        };
+
+        // The plane meshes are in normalized image coordinates, see `sync_image_planes`
+        let normalized = plane.affine().inverse().transform_point3(position).xy();
+        tool.second = Some(intrinsics.denormalize(normalized));
+    }
+
+    if let (Some(main), Some(second)) = (tool.main, tool.second) {
+        triangulation
+            .correspondences
+            .push(Correspondence { main, second });
+        tool.main = None;
+        tool.second = None;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn triangulate_correspondences(
+    mut triangulated: ResMut<TriangulatedPoints>,
+    triangulation: Res<Triangulation>,
+    intrinsics: Res<CameraIntrinsics>,
+    projection: Res<CameraProjection>,
+    size: Res<ImageSize>,
+    resolution: Res<ImageResolution>,
+    rng: Res<SeededRng>,
+    parents: ParentTransforms,
+    second: SecondRig,
+) {
+    triangulated.0.clear();
+
+    // The main camera rays only go through its optical centre under perspective
+    if projection.model != ProjectionModel::Perspective {
+        return;
+    }
+    let (Some(main_rig), Some(second_rig)) = (parents.rig(), second.transform()) else {
+        return;
+    };
+    let cameras = [main_rig.affine(), second_rig.affine()];
+
+    let mut noise =
+        triangulation
+            .noise
+            .sampler(&rng, TRIANGULATION_NOISE_STREAM, &size, &resolution);
+
+    for correspondence in &triangulation.correspondences {
+        let observed = Correspondence {
+            main: correspondence.main + noise(),
+            second: correspondence.second + noise(),
+        };
+        let views = |correspondence: &Correspondence| {
+            [View::Main, View::Second]
+                .map(|view| intrinsics.normalize(correspondence.get(view)))
+                .into_iter()
+                .zip(cameras)
+                .map(|(normalized, camera)| (camera, normalized))
+                .collect::<Vec<_>>()
+        };
+        let noisy = views(&observed);
+        let rays = [0, 1].map(|i| back_projected_ray(noisy[i].0, noisy[i].1));
+
+        triangulated.0.push(TriangulatedPoint {
+            observed,
+            rays,
+            dlt: triangulate_dlt(&noisy),
+            closest: closest_points(rays[0], rays[1]),
+            exact: triangulate_dlt(&views(correspondence)),
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn gizmo_triangulation(
+    mut gizmos: Gizmos,
+    mut cache: MeshMaterialCache,
+    tool: Res<CorrespondenceTool>,
+    triangulated: Res<TriangulatedPoints>,
+    intrinsics: Res<CameraIntrinsics>,
+    planes: Res<ImagePlanes>,
+    point_settings: Res<ImagePoints>,
+    parents: ParentTransforms,
+    second: SecondRig,
+) {
+    let (Some(main_rig), Some(second_rig)) = (parents.rig(), second.transform()) else {
+        return;
+    };
+    let depth = planes.main_depth();
+    let on_plane = |view: View, sensor: Vec2| {
+        let rig = match view {
+            View::Main => main_rig,
+            View::Second => second_rig,
+        };
+        rig.transform_point(intrinsics.back_project(sensor, depth))
+    };
+    let radius = point_settings.point_size / 2.;
+
+    let halves = [(View::Main, tool.main), (View::Second, tool.second)];
+    for (view, sensor) in halves {
+        if let Some(sensor) = sensor {
+            gizmos.sphere(
+                on_plane(view, sensor),
+                Quat::default(),
+                radius,
+                palettes::basic::WHITE,
+            );
+        }
+    }
+
+    for (index, point) in triangulated.0.iter().enumerate() {
+        let color = cache.color(index);
+        let estimate = point.midpoint().or(point.dlt);
+
+        for (view, ray) in [View::Main, View::Second].into_iter().zip(point.rays) {
+            gizmos.sphere(
+                on_plane(view, point.observed.get(view)),
+                Quat::default(),
+                radius,
+                color,
+            );
+
+            // A bit past the estimate, or to the furthest plane if there is none
+            let length = match estimate {
+                Some(estimate) => (estimate - ray.origin).dot(*ray.direction).abs() * 1.25,
+                None => planes.far_depth().abs(),
+            };
+            gizmos.line(ray.origin, ray.get_point(length), color.with_alpha(0.6));
+        }
+
+        if let Some((a, b)) = point.closest {
+            gizmos.line(a, b, palettes::basic::RED);
+            gizmos.sphere(
+                (a + b) / 2.,
+                Quat::default(),
+                radius,
+                palettes::basic::WHITE,
+            );
+        }
+        if let Some(dlt) = point.dlt {
+            gizmos.cuboid(
+                Transform::from_translation(dlt).with_scale(Vec3::splat(radius * 2.)),
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two cameras looking roughly the same way, and where they see `point`
+    fn views(point: Vec3) -> [(Affine3A, Vec2); 2] {
+        let cameras = [
+            Affine3A::IDENTITY,
+            Affine3A::from_rotation_translation(
+                Quat::from_rotation_y(-0.2),
+                Vec3::new(1.0, 0.1, 0.0),
+            ),
+        ];
+
+        cameras.map(|camera_to_world| {
+            let camera = camera_to_world.inverse().transform_point3(point);
+            (camera_to_world, camera.xy() / camera.z)
+        })
+    }
+
+    #[test]
+    fn dlt_round_trip() {
+        let point = Vec3::new(0.3, -0.4, 5.0);
+        let estimate = triangulate_dlt(&views(point)).unwrap();
+
+        assert!(estimate.distance(point) < 1e-4, "{estimate} != {point}");
+    }
+
+    #[test]
+    fn midpoint_round_trip() {
+        let point = Vec3::new(-0.7, 0.2, 3.0);
+        let [(a, x), (b, y)] = views(point);
+        let (on_a, on_b) =
+            closest_points(back_projected_ray(a, x), back_projected_ray(b, y)).unwrap();
+
+        assert!(on_a.distance(on_b) < 1e-4);
+        assert!(((on_a + on_b) / 2.).distance(point) < 1e-4);
+    }
+
+    #[test]
+    fn parallel_rays_have_no_closest_points() {
+        let ray = |x: f32| Ray3d::new(Vec3::new(x, 0.0, 0.0), Vec3::Z);
+
+        assert!(closest_points(ray(0.0), ray(1.0)).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_inspector_egui::{
-    bevy_egui::{EguiContext, EguiSettings},
+    bevy_egui::{EguiContext, EguiContexts, EguiSettings},
     bevy_inspector::ui_for_resource,
     egui,
     inspector_options::ReflectInspectorOptions,
@@ -8,7 +8,22 @@ use bevy_inspector_egui::{
     InspectorOptions,
 };
 
-use crate::{gizmos::GizmoSettings, ImagePlanes, ImagePoints, ImageSize};
+use crate::{
+    camera::{CameraExtrinsics, CameraIntrinsics, CameraProjection},
+    coords,
+    distortion::LensDistortion,
+    epipolar::EpipolarSettings,
+    gizmos::GizmoSettings,
+    homography::HomographySettings,
+    image_shapes::ImageShapes,
+    plane_homography::WorldPlane,
+    plane_image::PlaneImage,
+    point_labels::{ui_point_groups, LabelSettings},
+    point_set::PointSetSource,
+    second_camera::SecondCamera,
+    world_points::WorldPoints,
+    FirstPlaneHover, ImagePlanes, ImagePoints, ImageResolution, ImageSize,
+};
 
 /// Combine relevant resources into one place
 pub struct UiSettingsPlugin;
@@ -37,7 +52,11 @@ impl Plugin for UiSettingsPlugin {
             .register_type::<UiSettings>()
             .add_systems(
                 Update,
-                (ui, set_scale.run_if(resource_changed::<UiSettings>)),
+                (
+                    ui,
+                    hover_readout,
+                    set_scale.run_if(resource_changed::<UiSettings>),
+                ),
             )
             .add_plugins(
                 WorldInspectorPlugin::new()
@@ -59,13 +78,56 @@ fn ui(world: &mut World) {
         egui::ScrollArea::both().show(ui, |ui| {
             ui_for_resource::<ImagePlanes>(world, ui);
             ui_for_resource::<ImagePoints>(world, ui);
+            ui_for_resource::<PointSetSource>(world, ui);
+            ui_for_resource::<PlaneImage>(world, ui);
             ui_for_resource::<ImageSize>(world, ui);
+            ui_for_resource::<ImageResolution>(world, ui);
+            ui_for_resource::<CameraIntrinsics>(world, ui);
+            ui_for_resource::<CameraExtrinsics>(world, ui);
+            ui_for_resource::<CameraProjection>(world, ui);
+            ui_for_resource::<SecondCamera>(world, ui);
+            ui_for_resource::<EpipolarSettings>(world, ui);
+            ui_for_resource::<LensDistortion>(world, ui);
+            ui_for_resource::<WorldPoints>(world, ui);
+            ui_for_resource::<ImageShapes>(world, ui);
+            ui_for_resource::<HomographySettings>(world, ui);
+            ui_for_resource::<WorldPlane>(world, ui);
+            ui_for_resource::<LabelSettings>(world, ui);
+            ui_point_groups(world, ui);
             ui_for_resource::<GizmoSettings>(world, ui);
             ui_for_resource::<UiSettings>(world, ui);
         });
     });
 }
 
+/// The position under the pointer on the main image plane, in all coordinate systems
+fn hover_readout(
+    mut contexts: EguiContexts,
+    hover: Res<FirstPlaneHover>,
+    intrinsics: Res<CameraIntrinsics>,
+    size: Res<ImageSize>,
+    resolution: Res<ImageResolution>,
+) {
+    let Some(hover) = **hover else {
+        return;
+    };
+
+    let metric = intrinsics.denormalize(hover.normalized);
+    let pixel = coords::metric_to_pixel(metric, **size, **resolution);
+    let ndc = coords::metric_to_ndc(metric, **size);
+
+    egui::show_tooltip_at_pointer(
+        contexts.ctx_mut(),
+        egui::LayerId::background(),
+        egui::Id::new("hover readout"),
+        |ui| {
+            ui.label(format!("metric: ({:.3}, {:.3})", metric.x, metric.y));
+            ui.label(format!("pixel: ({:.1}, {:.1})", pixel.x, pixel.y));
+            ui.label(format!("NDC: ({:.3}, {:.3})", ndc.x, ndc.y));
+        },
+    );
+}
+
 fn set_scale(mut commands: Commands, settings: Res<UiSettings>) {
     commands.insert_resource(EguiSettings {
         scale_factor: settings.ui_scale,
@@ -1,4 +1,5 @@
-use bevy::{prelude::*, render::camera::Viewport, window::PrimaryWindow};
+use bevy::{ecs::system::SystemParam, prelude::*, render::camera::Viewport, window::PrimaryWindow};
+use bevy_mod_picking::pointer::Location;
 
 pub struct ViewportCameraPlugin;
 
@@ -56,3 +57,31 @@ fn add_viewport_camera(
         });
     }
 }
+
+/// Find which camera a pointer is interacting with, for cameras sharing a window
+#[derive(SystemParam)]
+pub struct PointerCameras<'w, 's> {
+    primary_window: Query<'w, 's, Entity, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+
+impl PointerCameras<'_, '_> {
+    /// The topmost camera which the pointer is over
+    pub fn under_pointer(&self, location: &Location) -> Option<(&Camera, &GlobalTransform)> {
+        self.cameras
+            .iter()
+            .filter(|(camera, _)| location.is_in_viewport(camera, &self.primary_window))
+            .max_by_key(|(camera, _)| camera.order)
+    }
+
+    /// The ray through the pointer from the camera it is over
+    pub fn ray(&self, location: &Location) -> Option<Ray3d> {
+        let (camera, camera_transform) = self.under_pointer(location)?;
+        let viewport_min = camera
+            .logical_viewport_rect()
+            .map(|rect| rect.min)
+            .unwrap_or_default();
+
+        camera.viewport_to_world(camera_transform, location.position - viewport_min)
+    }
+}
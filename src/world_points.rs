@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{inspector_options::ReflectInspectorOptions, InspectorOptions};
+use bevy_mod_picking::{
+    events::{Drag, Pointer},
+    prelude::PickableBundle,
+};
+use rand::Rng;
+
+use crate::{
+    camera::{CameraIntrinsics, CameraProjection},
+    egui_suppress::SuppressCameraWhilePressed,
+    material_mesh_cache::MeshMaterialCache,
+    seeded_rng::SeededRng,
+    viewport_camera::PointerCameras,
+    ImageSize, ParentTransforms,
+};
+
+/// Points which live in the world and get imaged by the camera
+pub struct WorldPointsPlugin;
+
+impl Plugin for WorldPointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldPoints>()
+            .register_type::<WorldPoints>()
+            .register_type::<WorldPoint>()
+            .register_type::<WorldPointProjection>()
+            .add_systems(
+                Update,
+                (
+                    generate_world_points.run_if(
+                        resource_changed::<WorldPoints>.or_else(resource_changed::<SeededRng>),
+                    ),
+                    drag_world_points,
+                    project_world_points,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct WorldPoints {
+    num_points: usize,
+
+    /// Depth range (in camera space at the time of generation) to place points within
+    #[inspector(min = 0.1)]
+    min_depth: f32,
+    #[inspector(min = 0.1)]
+    max_depth: f32,
+}
+
+impl Default for WorldPoints {
+    fn default() -> Self {
+        Self {
+            num_points: 3,
+            min_depth: 2.0,
+            max_depth: 6.0,
+        }
+    }
+}
+
+/// A point in the world.
+/// Drag it around to move it parallel to the view, hold shift while dragging to change its depth.
+#[derive(Debug, Clone, Copy, Component, Reflect)]
+pub struct WorldPoint {
+    pub index: usize,
+}
+
+/// Where a [`WorldPoint`] lands on the image planes, as normalized image coordinates.
+/// See [`CameraProjection::point_on_plane`] for where that is on a given plane.
+#[derive(Debug, Default, Clone, Copy, Component, Reflect, Deref)]
+pub struct WorldPointProjection(pub Option<Vec2>);
+
+/// Stream of [`SeededRng`] used for [`WorldPoint`] positions
+const WORLD_POINTS_STREAM: u64 = 2;
+
+#[allow(clippy::too_many_arguments)]
+fn generate_world_points(
+    mut commands: Commands,
+    mut cache: MeshMaterialCache,
+    settings: Res<WorldPoints>,
+    size: Res<ImageSize>,
+    intrinsics: Res<CameraIntrinsics>,
+    parents: ParentTransforms,
+    rng: Res<SeededRng>,
+    existing: Query<Entity, With<WorldPoint>>,
+) {
+    for entity in &existing {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let (Some(world_transform), Some(rig_transform)) = (parents.world(), parents.rig()) else {
+        return;
+    };
+    let camera_to_world_local = world_transform.affine().inverse() * rig_transform.affine();
+
+    let rect = Rectangle::new(size.x, size.y);
+    let (min_depth, max_depth) = (
+        settings.min_depth.min(settings.max_depth),
+        settings.min_depth.max(settings.max_depth),
+    );
+
+    let mut rng = rng.stream(WORLD_POINTS_STREAM);
+    for index in 0..settings.num_points {
+        let sensor = rect.sample_interior(&mut rng);
+        let depth = rng.gen_range(min_depth..=max_depth);
+        let pos = camera_to_world_local.transform_point3(intrinsics.back_project(sensor, depth));
+
+        commands.entity(parents.world_entity()).with_children(|b| {
+            b.spawn((
+                MaterialMeshBundle {
+                    mesh: cache.mesh::<Cuboid>(),
+                    material: cache.material(index),
+                    transform: Transform::from_translation(pos).with_scale(Vec3::splat(0.1)),
+                    ..default()
+                },
+                PickableBundle::default(),
+                SuppressCameraWhilePressed,
+                WorldPoint { index },
+                WorldPointProjection::default(),
+                Name::new(format!("world-point-{index}")),
+            ));
+        });
+    }
+}
+
+fn drag_world_points(
+    mut drag_events: EventReader<Pointer<Drag>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    cameras: PointerCameras,
+    parents: Query<&GlobalTransform, Without<WorldPoint>>,
+    mut points: Query<(&mut Transform, &GlobalTransform, &Parent), With<WorldPoint>>,
+) {
+    for drag in drag_events.read() {
+        let Ok((mut transform, global_transform, parent)) = points.get_mut(drag.target) else {
+            continue;
+        };
+        let Ok(parent) = parents.get(**parent) else {
+            continue;
+        };
+
+        let Some((_, camera_transform)) = cameras.under_pointer(&drag.pointer_location) else {
+            continue;
+        };
+
+        let forward = camera_transform.forward();
+        let current = global_transform.translation();
+
+        let new_pos = if keys.pressed(KeyCode::ShiftLeft) {
+            current - forward * drag.event.delta.y * 0.01
+        } else {
+            let Some(ray) = cameras.ray(&drag.pointer_location) else {
+                continue;
+            };
+            let Some(distance) = ray.intersect_plane(current, InfinitePlane3d::new(*forward))
+            else {
+                continue;
+            };
+
+            ray.get_point(distance)
+        };
+
+        transform.translation = parent.affine().inverse().transform_point3(new_pos);
+    }
+}
+
+pub(crate) fn project_world_points(
+    parents: ParentTransforms,
+    projection: Res<CameraProjection>,
+    mut points: Query<(&GlobalTransform, &mut WorldPointProjection), With<WorldPoint>>,
+) {
+    let Some(rig) = parents.rig() else {
+        return;
+    };
+    let world_to_camera = rig.affine().inverse();
+
+    for (transform, mut normalized) in &mut points {
+        let point = world_to_camera.transform_point3(transform.translation());
+
+        normalized.0 = projection.project(point);
+    }
+}